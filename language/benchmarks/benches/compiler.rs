@@ -0,0 +1,90 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use compiler::{Compiler, VerifierPassSet};
+use criterion::{criterion_group, criterion_main, Criterion};
+use stdlib::build_stdlib;
+use types::{account_address::AccountAddress, account_config};
+
+/// Compiles 100 trivial scripts against the stdlib, so that the cost is dominated by setting up
+/// the dependencies rather than by compiling the code itself.
+fn compile_trivial_scripts(c: &mut Criterion) {
+    c.bench_function("compile_100_trivial_scripts", |b| {
+        b.iter(|| {
+            for _ in 0..100 {
+                Compiler::new(AccountAddress::default(), "main() { return; }")
+                    .into_compiled_program()
+                    .expect("script must compile");
+            }
+        })
+    });
+}
+
+/// Compiles a trivial script against the stdlib shared by every `Compiler` in the process, and
+/// against a stdlib built from scratch, as the compiler would have to if it didn't share one. The
+/// difference is the cost of building and verifying the stdlib.
+fn compile_with_shared_and_rebuilt_stdlib(c: &mut Criterion) {
+    c.bench_function("compile_with_shared_stdlib", |b| {
+        b.iter(|| {
+            Compiler::new(AccountAddress::default(), "main() { return; }")
+                .into_compiled_program()
+                .expect("script must compile")
+        })
+    });
+    c.bench_function("compile_with_rebuilt_stdlib", |b| {
+        b.iter(|| {
+            let mut compiler = Compiler::new(AccountAddress::default(), "main() { return; }");
+            compiler.skip_stdlib_deps = true;
+            compiler.extra_deps = build_stdlib(&account_config::core_code_address());
+            compiler
+                .into_compiled_program()
+                .expect("script must compile")
+        })
+    });
+}
+
+/// Compiles and verifies a module with every verifier pass, and without the type and memory
+/// safety analysis, the pass `verifier_passes` is mostly meant to skip. Timing isn't asserted in
+/// the compiler's tests as it depends on the machine; this is where the speedup is measured.
+fn compile_with_full_and_reduced_verification(c: &mut Criterion) {
+    let code = "
+        module M {
+            public sum(n: u64): u64 {
+                let i: u64;
+                let total: u64;
+                i = 0;
+                total = 0;
+                while (copy(i) < copy(n)) {
+                    total = move(total) + copy(i);
+                    i = move(i) + 1;
+                }
+                return move(total);
+            }
+        }
+        ";
+    let compile = |verifier_passes| {
+        let mut compiler = Compiler::new(AccountAddress::default(), code);
+        compiler.skip_stdlib_deps = true;
+        compiler.verifier_passes = Some(verifier_passes);
+        compiler.into_module_blob().expect("module must compile")
+    };
+    c.bench_function("compile_with_full_verification", |b| {
+        b.iter(|| compile(VerifierPassSet::all()))
+    });
+    c.bench_function("compile_without_type_and_memory_safety", |b| {
+        b.iter(|| {
+            compile(VerifierPassSet {
+                type_and_memory_safety: false,
+                ..VerifierPassSet::all()
+            })
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    compile_trivial_scripts,
+    compile_with_shared_and_rebuilt_stdlib,
+    compile_with_full_and_reduced_verification
+);
+criterion_main!(benches);
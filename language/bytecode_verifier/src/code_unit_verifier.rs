@@ -17,13 +17,59 @@ use crate::{
     type_memory_safety::TypeAndMemorySafetyAnalysis,
 };
 
+/// The passes `CodeUnitVerifier` runs over function bodies. Every pass is selected by default;
+/// skipping some is meant to speed up the edit-compile loop during development. Code that was
+/// verified with a reduced pass set may be unsafe and must be fully verified before it is
+/// published.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VerifierPassSet {
+    /// Check that every basic block leaves the stack balanced.
+    pub stack_usage: bool,
+    /// Check that the acquires annotations match the global resources functions access.
+    pub acquires: bool,
+    /// The type, memory and reference safety analysis. This is the most expensive pass. It
+    /// relies on stack usage being correct, so `stack_usage` is run whenever this is set.
+    pub type_and_memory_safety: bool,
+}
+
+impl VerifierPassSet {
+    /// Every pass, i.e. the checks `VerifiedModule::new` runs.
+    pub fn all() -> Self {
+        VerifierPassSet {
+            stack_usage: true,
+            acquires: true,
+            type_and_memory_safety: true,
+        }
+    }
+
+    /// Returns whether this runs every pass.
+    pub fn is_all(&self) -> bool {
+        *self == Self::all()
+    }
+}
+
+impl Default for VerifierPassSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
 pub struct CodeUnitVerifier<'a> {
     module: &'a CompiledModule,
+    passes: VerifierPassSet,
 }
 
 impl<'a> CodeUnitVerifier<'a> {
     pub fn verify(module: &'a CompiledModule) -> Vec<VerificationError> {
-        let verifier = Self { module };
+        Self::verify_with_passes(module, VerifierPassSet::all())
+    }
+
+    /// Like `verify`, but only runs the passes selected in `passes`.
+    pub fn verify_with_passes(
+        module: &'a CompiledModule,
+        passes: VerifierPassSet,
+    ) -> Vec<VerificationError> {
+        let verifier = Self { module, passes };
         verifier
             .module
             .function_defs()
@@ -67,14 +113,21 @@ impl<'a> CodeUnitVerifier<'a> {
         function_definition: &FunctionDefinition,
         cfg: &VMControlFlowGraph,
     ) -> Vec<VMStaticViolation> {
-        let errors = StackUsageVerifier::verify(self.module, function_definition, cfg);
-        if !errors.is_empty() {
-            return errors;
+        if self.passes.stack_usage || self.passes.type_and_memory_safety {
+            let errors = StackUsageVerifier::verify(self.module, function_definition, cfg);
+            if !errors.is_empty() {
+                return errors;
+            }
+        }
+        if self.passes.acquires {
+            let errors = AcquiresVerifier::verify(self.module, function_definition);
+            if !errors.is_empty() {
+                return errors;
+            }
         }
-        let errors = AcquiresVerifier::verify(self.module, function_definition);
-        if !errors.is_empty() {
-            return errors;
+        if self.passes.type_and_memory_safety {
+            return TypeAndMemorySafetyAnalysis::verify(self.module, function_definition, cfg);
         }
-        TypeAndMemorySafetyAnalysis::verify(self.module, function_definition, cfg)
+        vec![]
     }
 }
@@ -22,12 +22,12 @@ mod unit_tests;
 pub mod verifier;
 
 pub use check_duplication::DuplicationChecker;
-pub use code_unit_verifier::CodeUnitVerifier;
+pub use code_unit_verifier::{CodeUnitVerifier, VerifierPassSet};
 pub use resources::ResourceTransitiveChecker;
 pub use signature::SignatureChecker;
 pub use stack_usage_verifier::StackUsageVerifier;
 pub use struct_defs::RecursiveStructDefChecker;
 pub use verifier::{
-    verify_main_signature, verify_module_dependencies, verify_script_dependencies, VerifiedModule,
-    VerifiedScript,
+    verify_main_signature, verify_module_dependencies, verify_module_with_passes,
+    verify_script_dependencies, VerifiedModule, VerifiedScript,
 };
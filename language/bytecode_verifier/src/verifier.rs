@@ -3,8 +3,10 @@
 
 //! This module contains the public APIs supported by the bytecode verifier.
 use crate::{
-    check_duplication::DuplicationChecker, code_unit_verifier::CodeUnitVerifier,
-    resources::ResourceTransitiveChecker, signature::SignatureChecker,
+    check_duplication::DuplicationChecker,
+    code_unit_verifier::{CodeUnitVerifier, VerifierPassSet},
+    resources::ResourceTransitiveChecker,
+    signature::SignatureChecker,
     struct_defs::RecursiveStructDefChecker,
 };
 use failure::Error;
@@ -149,6 +151,32 @@ impl<'a> fmt::Display for VerifiedProgram<'a> {
     }
 }
 
+/// Runs the checks of `VerifiedModule::new` over `module`, with only the function body passes
+/// selected in `passes`, and returns the errors found.
+///
+/// The module-level checks (duplication, signatures, resources and recursive structs) are cheap
+/// and the function body passes rely on them, so they always run. There is no `VerifiedModule`
+/// to return, since a reduced pass set doesn't establish what a `VerifiedModule` guarantees.
+pub fn verify_module_with_passes(
+    module: &CompiledModule,
+    passes: VerifierPassSet,
+) -> Vec<VerificationError> {
+    // All CompiledModule instances are statically guaranteed to be bounds checked, so there's
+    // no need for more checking.
+    let mut errors = DuplicationChecker::new(module).verify();
+    if errors.is_empty() {
+        errors.append(&mut SignatureChecker::new(module).verify());
+        errors.append(&mut ResourceTransitiveChecker::new(module).verify());
+    }
+    if errors.is_empty() {
+        errors.append(&mut RecursiveStructDefChecker::new(module).verify());
+    }
+    if errors.is_empty() {
+        errors.append(&mut CodeUnitVerifier::verify_with_passes(module, passes));
+    }
+    errors
+}
+
 /// A module that has been verified for internal consistency.
 ///
 /// This does not include cross-module checking -- that needs to be done separately.
@@ -163,19 +191,7 @@ impl VerifiedModule {
     /// There is a partial order on the checks. For example, the duplication check must precede the
     /// structural recursion check. In general, later checks are more expensive.
     pub fn new(module: CompiledModule) -> Result<Self, (CompiledModule, Vec<VerificationError>)> {
-        // All CompiledModule instances are statically guaranteed to be bounds checked, so there's
-        // no need for more checking.
-        let mut errors = DuplicationChecker::new(&module).verify();
-        if errors.is_empty() {
-            errors.append(&mut SignatureChecker::new(&module).verify());
-            errors.append(&mut ResourceTransitiveChecker::new(&module).verify());
-        }
-        if errors.is_empty() {
-            errors.append(&mut RecursiveStructDefChecker::new(&module).verify());
-        }
-        if errors.is_empty() {
-            errors.append(&mut CodeUnitVerifier::verify(&module));
-        }
+        let errors = verify_module_with_passes(&module, VerifierPassSet::all());
         if errors.is_empty() {
             Ok(VerifiedModule(module))
         } else {
@@ -6,7 +6,7 @@ use crate::{
     parser::ast::{
         self, BinOp, Block, Builtin, Cmd, CopyableVal, Exp, Field, Function, FunctionBody,
         FunctionCall, FunctionSignature as AstFunctionSignature, FunctionVisibility, IfElse,
-        LValue, LValue_, Loop, ModuleDefinition, ModuleIdent, ModuleName, Program, Script,
+        LValue, LValue_, Loc, Loop, ModuleDefinition, ModuleIdent, ModuleName, Program, Script,
         Statement, StructDefinition as MoveStruct, StructDefinitionFields, Type, TypeVar, UnaryOp,
         Var, Var_, While,
     },
@@ -17,14 +17,14 @@ use std::{
     clone::Clone,
     collections::{
         hash_map::Entry::{Occupied, Vacant},
-        HashMap, VecDeque,
+        BTreeMap, HashMap, VecDeque,
     },
 };
 use types::{account_address::AccountAddress, byte_array::ByteArray};
 use vm::{
     access::ModuleAccess,
     file_format::{
-        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeUnit, CompiledModule,
+        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeOffset, CodeUnit, CompiledModule,
         CompiledModuleMut, CompiledProgram, CompiledScript, CompiledScriptMut, FieldDefinition,
         FieldDefinitionIndex, FunctionDefinition, FunctionDefinitionIndex, FunctionHandle,
         FunctionHandleIndex, FunctionSignature, FunctionSignatureIndex, Kind, LocalsSignature,
@@ -36,6 +36,23 @@ use vm::{
     printers::TableAccess,
 };
 
+/// The source locations of the bytecode of a function. Each entry pairs the offset of the first
+/// instruction compiled from a statement with the location of the statement, in increasing
+/// order of offsets. An instruction comes from the last statement starting at or before it.
+pub type FunctionSourceMap = Vec<(CodeOffset, Loc)>;
+
+/// The source maps of the functions with code of a module, by function name.
+pub type ModuleSourceMap = BTreeMap<String, FunctionSourceMap>;
+
+/// The source maps of a compiled program.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    /// The source maps of the modules, in the order they are declared in the program.
+    pub modules: Vec<ModuleSourceMap>,
+    /// The source map of the script's `main`.
+    pub script: FunctionSourceMap,
+}
+
 #[derive(Debug, Default)]
 struct LoopInfo {
     start_loc: usize,
@@ -146,6 +163,7 @@ struct FunctionFrame {
     max_stack_depth: i64,
     cur_stack_depth: i64,
     loops: Vec<LoopInfo>,
+    source_map: FunctionSourceMap,
 }
 
 impl FunctionFrame {
@@ -1105,6 +1123,15 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
     module: &ModuleDefinition,
     modules: impl IntoIterator<Item = &'a T>,
 ) -> Result<CompiledModule> {
+    Ok(compile_module_with_source_map(address, module, modules)?.0)
+}
+
+/// Compile a module, also returning the source map of its functions.
+pub fn compile_module_with_source_map<'a, T: 'a + ModuleAccess>(
+    address: &AccountAddress,
+    module: &ModuleDefinition,
+    modules: impl IntoIterator<Item = &'a T>,
+) -> Result<(CompiledModule, ModuleSourceMap)> {
     // Convert to &CompiledModule as that's what's used throughout internally.
     let modules = modules.into_iter().map(|module| module.as_module());
 
@@ -1118,7 +1145,7 @@ fn compile_module_impl<'a>(
     address: &AccountAddress,
     module: &ModuleDefinition,
     scope: ModuleScope<'a>,
-) -> Result<CompiledModule> {
+) -> Result<(CompiledModule, ModuleSourceMap)> {
     let mut compiler = Compiler::new(scope);
 
     // Create an empty locals signature with index 0.
@@ -1147,6 +1174,7 @@ fn compile_module_impl<'a>(
     for (name, function) in &module.functions {
         compiler.define_function(name.name_ref(), &function)?;
     }
+    let mut source_map = ModuleSourceMap::new();
     for (name, function) in &module.functions {
         match &function.body {
             FunctionBody::Move { locals, code } => {
@@ -1156,20 +1184,22 @@ fn compile_module_impl<'a>(
                 //         in build_function_signature
                 //       change the API so that it can be reused
                 let k = build_type_formal_map(&function.signature.type_formals);
-                let compiled_code =
+                let (compiled_code, function_source_map) =
                     compiler.compile_function(&k, &function.signature.formals, locals, code)?;
                 compiler
                     .scope
                     .publish_code(name.name_ref(), compiled_code)?;
+                source_map.insert(name.name_ref().to_string(), function_source_map);
             }
             FunctionBody::Native => (),
         }
     }
-    compiler
+    let compiled_module = compiler
         .scope
         .module
         .freeze()
-        .map_err(|errs| InternalCompilerError::BoundsCheckErrors(errs).into())
+        .map_err(InternalCompilerError::BoundsCheckErrors)?;
+    Ok((compiled_module, source_map))
 }
 
 //
@@ -1177,24 +1207,20 @@ fn compile_module_impl<'a>(
 //
 
 /// Compile a transaction program.
-pub fn compile_program_2<'a, T: 'a + ModuleAccess>(
+pub fn compile_program<'a, T: 'a + ModuleAccess>(
     address: &AccountAddress,
     program: &Program,
     deps: impl IntoIterator<Item = &'a T>,
 ) -> Result<CompiledProgram> {
-    // Normalize into a Vec<&CompiledModule>.
-    let deps: Vec<&CompiledModule> = deps.into_iter().map(|dep| dep.as_module()).collect();
-
-    // This is separate to avoid unnecessary code gen due to monomorphization.
-    compile_program_impl(address, program, deps)
+    Ok(compile_program_with_source_map(address, program, deps)?.0)
 }
 
-/// Compile a transaction program.
-pub fn compile_program<'a, T: 'a + ModuleAccess>(
+/// Compile a transaction program, also returning its source map.
+pub fn compile_program_with_source_map<'a, T: 'a + ModuleAccess>(
     address: &AccountAddress,
     program: &Program,
     deps: impl IntoIterator<Item = &'a T>,
-) -> Result<CompiledProgram> {
+) -> Result<(CompiledProgram, SourceMap)> {
     // Normalize into a Vec<&CompiledModule>.
     let deps: Vec<&CompiledModule> = deps.into_iter().map(|dep| dep.as_module()).collect();
 
@@ -1206,21 +1232,28 @@ fn compile_program_impl(
     address: &AccountAddress,
     program: &Program,
     deps: Vec<&CompiledModule>,
-) -> Result<CompiledProgram> {
+) -> Result<(CompiledProgram, SourceMap)> {
     // Compile modules in the program
     let mut modules = vec![];
+    let mut module_source_maps = vec![];
     for m in &program.modules {
-        let module = {
+        let (module, module_source_map) = {
             let deps = deps.iter().copied().chain(&modules);
-            compile_module(address, &m, deps)?
+            compile_module_with_source_map(address, &m, deps)?
         };
         modules.push(module);
+        module_source_maps.push(module_source_map);
     }
 
     let deps: Vec<_> = deps.into_iter().chain(modules.iter()).collect();
-    let compiled_script = compile_script_impl(address, &program.script, deps)?;
-
-    Ok(CompiledProgram::new(modules, compiled_script))
+    let (compiled_script, script_source_map) =
+        compile_script_with_source_map_impl(address, &program.script, deps)?;
+
+    let source_map = SourceMap {
+        modules: module_source_maps,
+        script: script_source_map,
+    };
+    Ok((CompiledProgram::new(modules, compiled_script), source_map))
 }
 
 /// Compile a script.
@@ -1238,6 +1271,14 @@ pub fn compile_script_impl(
     script: &Script,
     deps: Vec<&CompiledModule>,
 ) -> Result<CompiledScript> {
+    Ok(compile_script_with_source_map_impl(address, script, deps)?.0)
+}
+
+fn compile_script_with_source_map_impl(
+    address: &AccountAddress,
+    script: &Script,
+    deps: Vec<&CompiledModule>,
+) -> Result<(CompiledScript, FunctionSourceMap)> {
     // Compile transaction script
     let compiled_script = CompiledScriptMut::default();
 
@@ -1269,10 +1310,11 @@ pub fn compile_script_impl(
     }
 
     // Compile the main function.
-    compiler.scope.script.main = compiler.compile_main(&script.main)?;
+    let (main, source_map) = compiler.compile_main(&script.main)?;
+    compiler.scope.script.main = main;
 
     match compiler.scope.script.freeze() {
-        Ok(compiled_script) => Ok(compiled_script),
+        Ok(compiled_script) => Ok((compiled_script, source_map)),
         Err(errs) => bail_err!(InternalCompilerError::BoundsCheckErrors(errs)),
     }
 }
@@ -1445,7 +1487,7 @@ impl<S: Scope + Sized> Compiler<S> {
     }
 
     // Compile a main function in a Script.
-    fn compile_main(&mut self, main: &Function) -> Result<FunctionDefinition> {
+    fn compile_main(&mut self, main: &Function) -> Result<(FunctionDefinition, FunctionSourceMap)> {
         // make main entry point
         let main_name = "main".to_string();
         let main_name_idx = self.make_string(&main_name)?;
@@ -1458,18 +1500,19 @@ impl<S: Scope + Sized> Compiler<S> {
         //       change the API so that it can be reused
         let k = build_type_formal_map(&main.signature.type_formals);
         // compile script
-        let code = match &main.body {
+        let (code, source_map) = match &main.body {
             FunctionBody::Move { code, locals } => {
                 self.compile_function(&k, &main.signature.formals, locals, code)?
             }
             FunctionBody::Native => bail!("main() cannot be a native function"),
         };
-        Ok(FunctionDefinition {
+        let main = FunctionDefinition {
             function: fh_idx,
             flags: CodeUnit::PUBLIC,
             acquires_global_resources: vec![],
             code,
-        })
+        };
+        Ok((main, source_map))
     }
 
     //
@@ -1882,7 +1925,7 @@ impl<S: Scope + Sized> Compiler<S> {
         formals: &[(Var, Type)],
         locals: &[(Var_, Type)],
         body: &Block,
-    ) -> Result<CodeUnit> {
+    ) -> Result<(CodeUnit, FunctionSourceMap)> {
         let mut code = CodeUnit::default();
         let mut function_frame = FunctionFrame::new();
         for (var, t) in formals {
@@ -1903,7 +1946,7 @@ impl<S: Scope + Sized> Compiler<S> {
         } else {
             function_frame.max_stack_depth as u16
         };
-        Ok(code)
+        Ok((code, function_frame.source_map))
     }
 
     fn compile_block(
@@ -1919,6 +1962,16 @@ impl<S: Scope + Sized> Compiler<S> {
         };
         for stmt in &body.stmts {
             debug!("{}", stmt);
+            let span = match stmt {
+                Statement::CommandStatement(command) => Some(command.span),
+                Statement::WhileStatement(while_) => Some(while_.cond.span),
+                Statement::IfElseStatement(if_else) => Some(if_else.cond.span),
+                Statement::LoopStatement(_) | Statement::EmptyStatement => None,
+            };
+            if let Some(span) = span {
+                let offset = code.code.len() as CodeOffset;
+                function_frame.source_map.push((offset, span));
+            }
             let stmt_info;
             match stmt {
                 Statement::CommandStatement(command) => {
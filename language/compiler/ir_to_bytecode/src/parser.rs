@@ -38,6 +38,9 @@ pub fn parse_script_or_module(s: &str) -> Result<ast::ScriptOrModule> {
 
 /// Given the raw input of a file, creates a `Program` struct
 /// Fails with `Err(_)` if the text cannot be parsed
+///
+/// Only the first syntax error is reported: the grammar has no error recovery, so parsing stops
+/// as soon as it fails.
 pub fn parse_program(program_str: &str) -> Result<ast::Program> {
     let stripped_string = &strip_comments(program_str);
     let parser = syntax::ProgramParser::new();
@@ -47,6 +50,38 @@ pub fn parse_program(program_str: &str) -> Result<ast::Program> {
     }
 }
 
+/// Like `parse_program`, but prefixes the error with `name` and the line the error is on, e.g.
+/// `foo.mvir:12`.
+pub fn parse_program_with_name(name: &str, program_str: &str) -> Result<ast::Program> {
+    let stripped_string = &strip_comments(program_str);
+    let parser = syntax::ProgramParser::new();
+    match parser.parse(stripped_string) {
+        Ok(program) => Ok(program),
+        Err(e) => {
+            let location = match &e {
+                ParseError::InvalidToken { location } => Some(*location),
+                ParseError::UnrecognizedToken {
+                    token: Some((l, _, _)),
+                    ..
+                }
+                | ParseError::ExtraToken { token: (l, _, _) } => Some(*l),
+                ParseError::UnrecognizedToken { token: None, .. } => Some(stripped_string.len()),
+                ParseError::User { .. } => None,
+            };
+            // Stripping comments keeps the line breaks, so lines can be counted in either string.
+            let prefix = match location {
+                Some(location) => format!(
+                    "{}:{}",
+                    name,
+                    stripped_string[..location].matches('\n').count() + 1
+                ),
+                None => name.to_string(),
+            };
+            handle_error(e, program_str).map_err(|err| format_err!("{}: {}", prefix, err))
+        }
+    }
+}
+
 /// Given the raw input of a file, creates a `Script` struct
 /// Fails with `Err(_)` if the text cannot be parsed
 pub fn parse_script(script_str: &str) -> Result<ast::Script> {
@@ -276,6 +276,8 @@ pub struct Function {
     pub acquires: Vec<StructName>,
     /// The code for the procedure
     pub body: FunctionBody,
+    /// The location of the declaration, from its first token to the end of its body
+    pub span: Loc,
 }
 
 //**************************************************************************************************
@@ -815,6 +817,7 @@ impl Function {
         type_formals: Vec<(TypeVar, Kind)>,
         acquires: Vec<StructName>,
         body: FunctionBody,
+        span: Loc,
     ) -> Self {
         let signature = FunctionSignature::new(formals, return_type, type_formals);
         Function {
@@ -822,6 +825,7 @@ impl Function {
             signature,
             acquires,
             body,
+            span,
         }
     }
 }
@@ -0,0 +1,231 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Optional checks run by `Compiler` on top of parsing and compilation.
+
+use failure::prelude::*;
+use ir_to_bytecode::parser::ast::{self, FunctionVisibility, ModuleIdent};
+use std::collections::{BTreeMap, BTreeSet};
+use types::{account_address::AccountAddress, transaction::TransactionArgument};
+use vm::{
+    access::{ModuleAccess, ScriptAccess},
+    file_format::{CompiledModule, CompiledScript, SignatureToken},
+    views::StructDefinitionView,
+};
+
+/// Rejects any identifier in `code` that contains non-ASCII characters.
+///
+/// The IR grammar only admits ASCII identifiers, so such sources fail to parse regardless. This
+/// check runs on the raw source beforehand and names the offending identifier and its position,
+/// which the generic "Invalid Token" parse error doesn't.
+pub(crate) fn check_ascii_identifiers(code: &str) -> Result<()> {
+    for (line_idx, line) in code.lines().enumerate() {
+        // Comments are stripped before parsing, so they may contain anything.
+        let line = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        };
+        let mut start = None;
+        for (col, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+            let is_ident_char = c.is_alphanumeric() || c == '_' || c == '$';
+            match (start, is_ident_char) {
+                (None, true) => start = Some(col),
+                (Some(ident_start), false) => {
+                    let ident = &line[ident_start..col];
+                    ensure!(
+                        ident.is_ascii(),
+                        "non-ASCII identifier `{}` at line {}, column {}",
+                        ident,
+                        line_idx + 1,
+                        line[..ident_start].chars().count() + 1
+                    );
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every function in `entry_functions`, named `<module>.<function>`, is defined by a
+/// module of `program` and is public, since it couldn't be called as an entry point otherwise.
+/// The script's `main` is always public.
+pub(crate) fn check_entry_functions_public(
+    program: &ast::Program,
+    entry_functions: &[String],
+) -> Result<()> {
+    for qualified_name in entry_functions {
+        let function = program.modules.iter().find_map(|module| {
+            module
+                .functions
+                .iter()
+                .find(|(name, _)| format!("{}.{}", module.name, name) == *qualified_name)
+        });
+        match function {
+            Some((_, function)) => ensure!(
+                function.visibility == FunctionVisibility::Public,
+                "entry function `{}` must be public",
+                qualified_name
+            ),
+            None => bail!("entry function `{}` isn't defined", qualified_name),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every struct in `expected`, named `<module>.<struct>`, is defined by one of
+/// `modules` with exactly the given set of fields.
+pub(crate) fn check_struct_fields(
+    modules: &[CompiledModule],
+    expected: &BTreeMap<String, BTreeSet<String>>,
+) -> Result<()> {
+    for (qualified_name, expected_fields) in expected {
+        let mut fields = None;
+        for module in modules {
+            for struct_def in module.struct_defs() {
+                let view = StructDefinitionView::new(module, struct_def);
+                if format!("{}.{}", module.name(), view.name()) == *qualified_name {
+                    fields = Some(view.fields().map_or_else(BTreeSet::new, |fields| {
+                        fields.map(|field| field.name().to_string()).collect()
+                    }));
+                }
+            }
+        }
+        let fields = match fields {
+            Some(fields) => fields,
+            None => bail!(
+                "struct `{}` has expected fields but isn't defined",
+                qualified_name
+            ),
+        };
+        ensure!(
+            fields == *expected_fields,
+            "struct `{}` has fields {:?}, expected {:?}",
+            qualified_name,
+            fields,
+            expected_fields
+        );
+    }
+    Ok(())
+}
+
+/// Rejects a program whose modules import each other in a cycle, naming the modules along it,
+/// e.g. `A -> B -> C -> A`. Imports of `Transaction.<name>` and of `<address>.<name>` both refer
+/// to a module of the program if it is named `name`, since the program's modules are published
+/// under `address`.
+pub(crate) fn check_import_cycles(program: &ast::Program, address: &AccountAddress) -> Result<()> {
+    let names: BTreeSet<String> = program
+        .modules
+        .iter()
+        .map(|module| module.name.name())
+        .collect();
+    let mut edges: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for module in &program.modules {
+        let targets = module
+            .imports
+            .iter()
+            .filter_map(|import| match &import.ident {
+                ModuleIdent::Transaction(name) => Some(name.name()),
+                ModuleIdent::Qualified(ident) if ident.address == *address => {
+                    Some(ident.name.name())
+                }
+                ModuleIdent::Qualified(_) => None,
+            })
+            .filter(|name| names.contains(name));
+        edges
+            .entry(module.name.name())
+            .or_insert_with(Vec::new)
+            .extend(targets);
+    }
+
+    let mut done = BTreeSet::new();
+    for module in &program.modules {
+        let mut path = vec![];
+        if let Some(cycle) = find_cycle(&module.name.name(), &edges, &mut path, &mut done) {
+            bail!("cyclic module dependency: {}", cycle.join(" -> "));
+        }
+    }
+    Ok(())
+}
+
+/// Depth-first search for a cycle through `node`. `path` holds the modules currently being
+/// visited and `done` the ones known not to be on any cycle.
+fn find_cycle(
+    node: &str,
+    edges: &BTreeMap<String, Vec<String>>,
+    path: &mut Vec<String>,
+    done: &mut BTreeSet<String>,
+) -> Option<Vec<String>> {
+    if let Some(start) = path.iter().position(|visited| visited == node) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(node.to_string());
+        return Some(cycle);
+    }
+    if done.contains(node) {
+        return None;
+    }
+    path.push(node.to_string());
+    for target in edges.get(node).into_iter().flatten() {
+        if let Some(cycle) = find_cycle(target, edges, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(node.to_string());
+    None
+}
+
+/// Rejects any function defined by one of `modules` that has more than `max` type parameters.
+pub(crate) fn check_max_type_params(modules: &[CompiledModule], max: usize) -> Result<()> {
+    for module in modules {
+        for function_def in module.function_defs() {
+            let handle = module.function_handle_at(function_def.function);
+            let type_params = module
+                .function_signature_at(handle.signature)
+                .type_formals
+                .len();
+            ensure!(
+                type_params <= max,
+                "function `{}.{}` has {} type parameters, the maximum is {}",
+                module.name(),
+                module.string_at(handle.name),
+                type_params,
+                max
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Rejects `args` unless they match the parameters of the script's `main` in number and type.
+pub(crate) fn check_script_args(
+    script: &CompiledScript,
+    args: &[TransactionArgument],
+) -> Result<()> {
+    let handle = script.function_handle_at(script.main().function);
+    let formals = &script.function_signature_at(handle.signature).arg_types;
+    ensure!(
+        formals.len() == args.len(),
+        "script expects {} arguments, got {}",
+        formals.len(),
+        args.len()
+    );
+    for (idx, (formal, arg)) in formals.iter().zip(args).enumerate() {
+        let matches = match (formal, arg) {
+            (SignatureToken::U64, TransactionArgument::U64(_))
+            | (SignatureToken::Address, TransactionArgument::Address(_))
+            | (SignatureToken::ByteArray, TransactionArgument::ByteArray(_))
+            | (SignatureToken::String, TransactionArgument::String(_)) => true,
+            _ => false,
+        };
+        ensure!(
+            matches,
+            "argument {} is {:?}, but the script expects {:?}",
+            idx,
+            arg,
+            formal
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Non-fatal findings reported by the compiler, such as lint warnings.
+
+use ir_to_bytecode::parser::ast::Loc;
+
+/// How serious a `Diagnostic` is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    /// The code compiles but is likely to be wrong or unsafe.
+    Warning,
+}
+
+/// A finding about the source, anchored at the span it refers to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The byte range of the offending code in the source.
+    pub span: Loc,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String, span: Loc) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span,
+        }
+    }
+}
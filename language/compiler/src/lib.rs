@@ -1,27 +1,63 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+#![feature(non_exhaustive)]
+
+mod checks;
+pub mod diagnostics;
+pub mod lint;
+pub mod session;
 pub mod util;
+pub mod watch;
 
 #[cfg(test)]
 mod unit_tests;
 
-use bytecode_verifier::VerifiedModule;
+use crate::{
+    diagnostics::Diagnostic,
+    util::{InterfaceFile, ProgramDump},
+};
+use bytecode_verifier::{
+    verify_module_dependencies, verify_module_with_passes, verify_script_dependencies,
+    VerifiedModule, VerifiedScript,
+};
+use canonical_serialization::SimpleSerializer;
 use failure::prelude::*;
 use ir_to_bytecode::{
-    compiler::{compile_module, compile_program,compile_program_2},
-    parser::parse_program,
+    compiler::{compile_module, compile_program, compile_program_with_source_map, SourceMap},
+    parser::{ast, parse_program, parse_program_with_name},
+};
+use logger::prelude::*;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
 };
-use std::mem;
 use stdlib::stdlib_modules;
 use types::{
     account_address::AccountAddress,
-    transaction::{Program, TransactionArgument},
+    language_storage::ModuleId,
+    transaction::{Program, TransactionArgument, TransactionPayload},
 };
-use vm::file_format::{CompiledModule, CompiledProgram, CompiledScript};
+use vm::{
+    access::ModuleAccess,
+    errors::VerificationStatus,
+    file_format::{CompiledModule, CompiledProgram, CompiledScript},
+    file_format_common::BinaryConstants,
+};
+
+pub use bytecode_verifier::VerifierPassSet;
 
 /// An API for the compiler. Supports setting custom options.
+///
+/// New options may be added at any time, so outside of this crate a `Compiler` can't be built
+/// with a struct expression. Start from `Compiler::new` and set the options needed instead.
+///
+/// This relies on `#[non_exhaustive]`, which is still behind the `non_exhaustive` feature gate on
+/// the pinned nightly toolchain; it is only stable from Rust 1.40. The `#![feature]` at the top of
+/// this crate can be dropped once the toolchain moves past that.
 #[derive(Clone, Debug, Default)]
+#[non_exhaustive]
 pub struct Compiler<'a> {
     /// The address used as the sender for the compiler.
     pub address: AccountAddress,
@@ -31,155 +67,708 @@ pub struct Compiler<'a> {
     pub skip_stdlib_deps: bool,
     /// The address to use for stdlib.
     pub stdlib_address: AccountAddress,
+    /// The modules to use in place of the stdlib, e.g. to test changes to it in isolation. They
+    /// are treated as the stdlib in every respect: `skip_stdlib_deps` leaves them out and
+    /// `extra_deps` are linked in on top of them.
+    pub custom_stdlib: Option<Vec<VerifiedModule>>,
     /// Extra dependencies to compile with.
     pub extra_deps: Vec<VerifiedModule>,
+    /// A pinned set of serialized dependency modules. When set, these are used verbatim as the
+    /// full dependency set -- neither the stdlib nor `extra_deps` are consulted. Modules must be
+    /// listed in dependency order, as each one is checked against the ones preceding it.
+    pub frozen_deps: Option<Vec<Vec<u8>>>,
+    /// Reject identifiers containing non-ASCII characters, naming the offending identifier and
+    /// its position.
+    pub ascii_identifiers_only: bool,
+    /// Names that can stand in for an address in `import` declarations, e.g. `Std` in
+    /// `import Std.M;`. They are substituted textually before parsing, so source spans refer to
+    /// the substituted code.
+    pub address_aliases: BTreeMap<String, AccountAddress>,
+    /// Run the bytecode verifier over the compiled modules and script, restricted to these
+    /// passes. Verification is skipped if this is `None`. Skipping passes may let unsafe code
+    /// through, so a warning is logged whenever the set isn't complete.
+    pub verifier_passes: Option<VerifierPassSet>,
+    /// Run every pass of the bytecode verifier over the compiled modules and script, failing on
+    /// the first error, as if `verifier_passes` were `Some(VerifierPassSet::all())`. This is off
+    /// by default to keep the defaults backward compatible: the compiler didn't verify before this
+    /// option was added, and tests compile code that is meant to be rejected by the verifier.
+    /// `verifier_passes` takes precedence if it is set. `into_verified_program` always verifies.
+    pub verify: bool,
+    /// Module functions meant to be called as entry points, named `<module>.<function>`.
+    /// Compilation fails if any of them isn't defined or isn't public.
+    pub entry_functions: Vec<String>,
+    /// The set of fields each struct, named `<module>.<struct>`, is expected to have. Compilation
+    /// fails if a struct is missing or has fields added, removed or renamed. The order fields
+    /// are declared in doesn't matter, as the compiler always lays them out alphabetically, so
+    /// reordering them in the source doesn't change the compiled layout.
+    pub expected_struct_fields: BTreeMap<String, BTreeSet<String>>,
+    /// The maximum number of type parameters a module function may have, e.g. to match the limit
+    /// of the VM the code is going to be published to. Compilation fails, naming the function, if
+    /// any function has more.
+    pub max_type_params: Option<usize>,
+    /// Check that the arguments passed to `into_program` and `into_program_and_deps` match the
+    /// parameters of the script's `main` in number and type. Otherwise a mismatch only surfaces
+    /// when the program is executed.
+    pub validate_args: bool,
+    /// The name of the code, e.g. the path of the file it was read from. Parse errors are
+    /// prefixed with it and the line they occurred on, compilation errors with just the name.
+    pub source_name: Option<&'a str>,
+    /// The major version of the bytecode format written into the header of serialized scripts
+    /// and modules, e.g. to test how tooling handles blobs of another version. Defaults to the
+    /// current version, `BinaryConstants::VERSION_MAJOR`, which is the only one the table layout
+    /// and the deserializer support.
+    pub bytecode_version: Option<u8>,
+}
 
-    // The typical way this should be used is with functional record update syntax:
-    //
-    // let compiler = Compiler { address, code, ..Compiler::new() };
-    //
-    // Until the #[non_exhaustive] attribute is available (see
-    // https://github.com/rust-lang/rust/issues/44109), this workaround is required to make the
-    // syntax be mandatory.
-    #[allow(missing_docs)]
-    #[doc(hidden)]
-    pub _non_exhaustive: (),
+/// Why `Compiler::into_verified_program` failed.
+#[derive(Debug)]
+pub enum VerifyProgramError {
+    /// The code doesn't compile.
+    Compile(Error),
+    /// The code compiles but fails verification, with the errors of every module and the script.
+    Verify(Vec<VerificationStatus>),
 }
 
 impl<'a> Compiler<'a> {
+    /// Creates a compiler for `code`, sent from `address`, with every other option left at its
+    /// default: the stdlib is linked in and there are no extra dependencies. The options are
+    /// public fields, to be set on the result.
+    ///
+    /// ```
+    /// use compiler::Compiler;
+    /// use types::account_address::AccountAddress;
+    ///
+    /// let code = "main() { return; }";
+    /// let mut compiler = Compiler::new(AccountAddress::default(), code);
+    /// compiler.skip_stdlib_deps = true;
+    /// let program = compiler.into_compiled_program().unwrap();
+    /// assert!(program.modules.is_empty());
+    /// ```
+    ///
+    /// Struct expressions, even with functional record update, only compile within this crate:
+    ///
+    /// ```compile_fail
+    /// use compiler::Compiler;
+    ///
+    /// let compiler = Compiler {
+    ///     code: "main() { return; }",
+    ///     ..Compiler::default()
+    /// };
+    /// ```
+    pub fn new(address: AccountAddress, code: &'a str) -> Self {
+        Self {
+            address,
+            code,
+            ..Self::default()
+        }
+    }
+
+    /// Adds `deps` to the extra dependencies.
+    pub fn add_deps(&mut self, deps: Vec<VerifiedModule>) {
+        self.extra_deps.extend(deps);
+    }
 
-    pub fn add_deps(&mut self,deps: Vec<VerifiedModule>){
+    /// Replaces the extra dependencies with `deps`.
+    pub fn set_deps(&mut self, deps: Vec<VerifiedModule>) {
         self.extra_deps = deps;
     }
 
+    /// Deserializes and verifies each of the serialized modules in `blobs`, and adds them to the
+    /// extra dependencies. Nothing is added if any blob fails to deserialize or verify.
+    pub fn add_dep_blobs(&mut self, blobs: Vec<Vec<u8>>) -> Result<()> {
+        let mut deps = vec![];
+        for (idx, blob) in blobs.iter().enumerate() {
+            let module = CompiledModule::deserialize(blob)
+                .map_err(|err| format_err!("dependency {} can't be deserialized: {}", idx, err))?;
+            let module = VerifiedModule::new(module).map_err(|(_, errors)| {
+                format_err!("dependency {} failed verification: {:?}", idx, errors)
+            })?;
+            deps.push(module);
+        }
+        self.extra_deps.extend(deps);
+        Ok(())
+    }
+
+    /// Adds the modules described by `interfaces` to the extra dependencies, so that the code
+    /// can be compiled against them without their source.
+    pub fn with_interfaces(mut self, interfaces: Vec<InterfaceFile>) -> Result<Self> {
+        for interface in interfaces {
+            self.extra_deps.push(interface.into_verified_module()?);
+        }
+        Ok(self)
+    }
+
     /// Compiles into a `CompiledProgram` where the bytecode hasn't been serialized.
-    pub fn into_compiled_program(mut self) -> Result<CompiledProgram> {
+    pub fn into_compiled_program(self) -> Result<CompiledProgram> {
         Ok(self.compile_impl()?.0)
     }
 
+    /// Compiles into a `CompiledProgram` and also returns its source map, which maps bytecode
+    /// offsets back to the source locations of the statements they were compiled from.
+    pub fn into_compiled_program_and_source_map(self) -> Result<(CompiledProgram, SourceMap)> {
+        let (compiled_program, source_map, _) = self.compile_with_source_map()?;
+        Ok((compiled_program, source_map))
+    }
+
+    /// Compiles the program and renders its script and modules as pretty-printed JSON, an object
+    /// with the keys `script` and `modules`, for inspecting them without a disassembler. See
+    /// `util::ModuleDump` for what is included.
+    pub fn into_json(self) -> Result<String> {
+        let compiled_program = self.compile_impl()?.0;
+        Ok(serde_json::to_string_pretty(&ProgramDump::new(
+            &compiled_program,
+        ))?)
+    }
+
     /// Compiles into a `CompiledProgram` and also returns the dependencies.
-    pub fn into_compiled_program_and_deps(
+    pub fn into_compiled_program_and_deps(self) -> Result<(CompiledProgram, Vec<VerifiedModule>)> {
+        let (compiled_program, deps) = self.compile_impl()?;
+        Ok((compiled_program, deps.into_owned()))
+    }
+
+    /// Compiles the program and runs its modules and script through the bytecode verifier,
+    /// linking them against the dependencies.
+    ///
+    /// Fails with `VerifyProgramError::Compile` if the code doesn't compile. Otherwise it fails
+    /// with `VerifyProgramError::Verify`, listing the verification errors of every module and of
+    /// the script rather than just the first one. The two are kept apart, rather than reporting
+    /// everything as a list of `VMStatus`es, because a compile error has no status to map to and
+    /// callers usually handle the two differently. A module that fails verification isn't linked
+    /// against, so the code importing it may report dependency errors as well.
+    ///
+    /// The code is verified with every pass regardless of `verify` and `verifier_passes`, and the
+    /// errors are collected rather than failing on the first one. Turning verification off can't
+    /// be honoured here, since the `VerifiedModule`s and `VerifiedScript` returned can only be
+    /// produced by the verifier; compile with `into_compiled_program` to skip it.
+    pub fn into_verified_program(
         mut self,
-    ) -> Result<(CompiledProgram, Vec<VerifiedModule>)> {
-        self.compile_impl()
+    ) -> std::result::Result<(Vec<VerifiedModule>, VerifiedScript), VerifyProgramError> {
+        // Verification is done below, so that every error is collected.
+        self.verify = false;
+        self.verifier_passes = None;
+        let (compiled_program, deps) = self.compile_impl().map_err(VerifyProgramError::Compile)?;
+
+        let mut statuses = vec![];
+        let mut modules = vec![];
+        for (module_idx, module) in compiled_program.modules.into_iter().enumerate() {
+            let errors = match VerifiedModule::new(module) {
+                Ok(module) => {
+                    let errors = verify_module_dependencies(&module, deps.iter().chain(&modules));
+                    if errors.is_empty() {
+                        modules.push(module);
+                    }
+                    errors
+                }
+                Err((_, errors)) => errors,
+            };
+            statuses.extend(
+                errors
+                    .into_iter()
+                    .map(|error| VerificationStatus::Module(module_idx as u16, error)),
+            );
+        }
+        let script = match VerifiedScript::new(compiled_program.script) {
+            Ok(script) => {
+                let errors = verify_script_dependencies(&script, deps.iter().chain(&modules));
+                statuses.extend(errors.into_iter().map(VerificationStatus::Script));
+                Some(script)
+            }
+            Err((_, errors)) => {
+                statuses.extend(errors.into_iter().map(VerificationStatus::Script));
+                None
+            }
+        };
+
+        match script {
+            Some(script) if statuses.is_empty() => Ok((modules, script)),
+            _ => Err(VerifyProgramError::Verify(statuses)),
+        }
     }
 
     /// Compiles into a `CompiledScript`.
-    pub fn into_script(mut self) -> Result<CompiledScript> {
+    pub fn into_script(self) -> Result<CompiledScript> {
         let compiled_program = self.compile_impl()?.0;
         Ok(compiled_program.script)
     }
 
+    /// Compiles into a `CompiledScript` and also returns the dependencies.
+    pub fn into_script_and_deps(self) -> Result<(CompiledScript, Vec<VerifiedModule>)> {
+        let (compiled_program, deps) = self.compile_impl()?;
+        Ok((compiled_program.script, deps.into_owned()))
+    }
+
     /// Compiles the script into a serialized form.
-    pub fn into_script_blob(mut self) -> Result<Vec<u8>> {
+    pub fn into_script_blob(self) -> Result<Vec<u8>> {
         let compiled_program = self.compile_impl()?.0;
 
         let mut serialized_script = Vec::<u8>::new();
-        compiled_program.script.serialize(&mut serialized_script)?;
+        compiled_program
+            .script
+            .serialize_with_version(&mut serialized_script, self.major_version())?;
         Ok(serialized_script)
     }
 
     /// Compiles the module.
-    pub fn into_compiled_module(mut self) -> Result<CompiledModule> {
+    pub fn into_compiled_module(self) -> Result<CompiledModule> {
         Ok(self.compile_mod()?.0)
     }
 
     /// Compiles the module into a serialized form.
-    pub fn into_module_blob(mut self) -> Result<Vec<u8>> {
+    pub fn into_module_blob(self) -> Result<Vec<u8>> {
         let compiled_module = self.compile_mod()?.0;
 
         let mut serialized_module = Vec::<u8>::new();
-        compiled_module.serialize(&mut serialized_module)?;
+        compiled_module.serialize_with_version(&mut serialized_module, self.major_version())?;
         Ok(serialized_module)
     }
 
+    /// Compiles the program and serializes each of its modules, in declaration order.
+    pub fn into_module_blobs(self) -> Result<Vec<Vec<u8>>> {
+        let compiled_program = self.compile_impl()?.0;
+        self.serialize_modules(&compiled_program.modules)
+    }
+
+    /// Compiles the program and writes each of its modules to `writer`, in declaration order, as
+    /// its length in bytes as a little-endian `u32` followed by the serialized module. Only one
+    /// serialized module is held in memory at a time. Read the modules back with
+    /// `util::read_module_stream`.
+    pub fn serialize_modules_to<W: Write>(self, writer: &mut W) -> Result<()> {
+        let compiled_program = self.compile_impl()?.0;
+
+        let mut serialized_module = Vec::<u8>::new();
+        for module in &compiled_program.modules {
+            serialized_module.clear();
+            module.serialize_with_version(&mut serialized_module, self.major_version())?;
+            ensure!(
+                serialized_module.len() <= u32::max_value() as usize,
+                "{:?} is too large to be length-prefixed",
+                module.self_id()
+            );
+            writer.write_all(&(serialized_module.len() as u32).to_le_bytes())?;
+            writer.write_all(&serialized_module)?;
+        }
+        Ok(())
+    }
+
     /// Compiles the code and arguments into a `Program` -- the bytecode is serialized.
-    pub fn into_program_and_deps(mut self, args: Vec<TransactionArgument>) -> Result<(Program, Vec<VerifiedModule>,Vec<CompiledModule>)>  {
-        let (compiled_program,deps) = self.compile_impl()?;
+    pub fn into_program_and_deps(
+        self,
+        args: Vec<TransactionArgument>,
+    ) -> Result<(Program, Vec<VerifiedModule>, Vec<CompiledModule>)> {
+        let (compiled_program, deps) = self.compile_impl()?;
+        if self.validate_args {
+            checks::check_script_args(&compiled_program.script, &args)?;
+        }
 
         let mut serialized_script = Vec::<u8>::new();
-        compiled_program.script.serialize(&mut serialized_script)?;
-        let mut serialized_modules = vec![];
-        for m in compiled_program.modules.clone() {
-            let mut module = vec![];
-            m.serialize(&mut module).expect("module must serialize");
-            serialized_modules.push(module);
-        }
-        Ok((Program::new(serialized_script, serialized_modules, args),deps,compiled_program.modules.clone()))
+        compiled_program
+            .script
+            .serialize_with_version(&mut serialized_script, self.major_version())?;
+        let serialized_modules = self.serialize_modules(&compiled_program.modules)?;
+        Ok((
+            Program::new(serialized_script, serialized_modules, args),
+            deps.into_owned(),
+            compiled_program.modules,
+        ))
     }
 
     /// Compiles the code and arguments into a `Program` -- the bytecode is serialized.
-    pub fn into_program(mut self, args: Vec<TransactionArgument>) -> Result<Program> {
+    ///
+    /// Code consisting of a single module and no script gets the no-op script
+    /// `main() { return; }`, so the program publishes the module and does nothing else. Its
+    /// script is the same as if that script had been written out.
+    pub fn into_program(self, args: Vec<TransactionArgument>) -> Result<Program> {
         let compiled_program = self.compile_impl()?.0;
+        if self.validate_args {
+            checks::check_script_args(&compiled_program.script, &args)?;
+        }
 
         let mut serialized_script = Vec::<u8>::new();
-        compiled_program.script.serialize(&mut serialized_script)?;
-        let mut serialized_modules = vec![];
-        for m in compiled_program.modules {
-            let mut module = vec![];
-            m.serialize(&mut module).expect("module must serialize");
-            serialized_modules.push(module);
-        }
+        compiled_program
+            .script
+            .serialize_with_version(&mut serialized_script, self.major_version())?;
+        let serialized_modules = self.serialize_modules(&compiled_program.modules)?;
         Ok(Program::new(serialized_script, serialized_modules, args))
     }
 
-    /// Compiles the code and arguments into a `Program` -- the bytecode is serialized.
-    pub fn into_program_2(mut self, args: Vec<TransactionArgument>,deps:Vec<CompiledModule>) -> Result<Program> {
-        //self.add_deps(deps.into());
-        let deps_std = self.add_std_deps(self.extra_deps.clone() );
-        let compiled_program = self.compile_impl_2(deps_std)?;
+    /// Compiles the code and arguments into the payload of a transaction, see
+    /// `TransactionPayload::from_program`.
+    pub fn into_transaction_payload(
+        self,
+        args: Vec<TransactionArgument>,
+    ) -> Result<TransactionPayload> {
+        Ok(TransactionPayload::from_program(self.into_program(args)?))
+    }
+
+    /// Compiles the code once and serializes a `Program` for each of `arg_sets`, in order. Each
+    /// program is written as a frame: its length in bytes as a little-endian `u32`, followed by
+    /// its canonical serialization. Split the frames again with `util::read_framed_programs`.
+    pub fn into_framed_programs(self, arg_sets: Vec<Vec<TransactionArgument>>) -> Result<Vec<u8>> {
+        let compiled_program = self.compile_impl()?.0;
 
         let mut serialized_script = Vec::<u8>::new();
-        compiled_program.script.serialize(&mut serialized_script)?;
-        let mut serialized_modules = vec![];
-        for m in compiled_program.modules {
-            let mut module = vec![];
-            m.serialize(&mut module).expect("module must serialize");
-            serialized_modules.push(module);
+        compiled_program
+            .script
+            .serialize_with_version(&mut serialized_script, self.major_version())?;
+        let serialized_modules = self.serialize_modules(&compiled_program.modules)?;
+
+        let mut frames = vec![];
+        for (idx, args) in arg_sets.into_iter().enumerate() {
+            if self.validate_args {
+                checks::check_script_args(&compiled_program.script, &args)
+                    .map_err(|err| format_err!("argument set {}: {}", idx, err))?;
+            }
+            let program = Program::new(serialized_script.clone(), serialized_modules.clone(), args);
+            let frame = SimpleSerializer::<Vec<u8>>::serialize(&program)?;
+            ensure!(
+                frame.len() <= u32::max_value() as usize,
+                "program {} is too large to be length-prefixed",
+                idx
+            );
+            frames.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            frames.extend_from_slice(&frame);
         }
-        Ok(Program::new(serialized_script, serialized_modules, args))
+        Ok(frames)
     }
 
-    fn compile_impl_2(&mut self,deps:Vec<VerifiedModule>) -> Result<CompiledProgram> {
-        let parsed_program = parse_program(self.code)?;
-        //let deps = self.deps();
-        let compiled_program = compile_program_2(&self.address, &parsed_program, &deps)?;
-        Ok(compiled_program)
+    /// Compiles the code into a `Program`, binding `args` to the script's parameters by name.
+    ///
+    /// Fails if a parameter has no argument or an argument doesn't name a parameter.
+    pub fn into_program_named(
+        self,
+        mut args: BTreeMap<String, TransactionArgument>,
+    ) -> Result<Program> {
+        let parsed_program = self.parse()?;
+        let mut ordered_args = vec![];
+        for (var, _) in &parsed_program.script.main.signature.formals {
+            let arg = args
+                .remove(var.name())
+                .ok_or_else(|| format_err!("missing argument `{}`", var.name()))?;
+            ordered_args.push(arg);
+        }
+        ensure!(
+            args.is_empty(),
+            "unknown arguments: {:?}",
+            args.keys().collect::<Vec<_>>()
+        );
+        self.into_program(ordered_args)
+    }
+
+    /// Compiles the code and arguments into a `Program`, linking against exactly `deps`: neither
+    /// the stdlib, `extra_deps` nor `frozen_deps` are consulted.
+    pub fn into_program_with_deps(
+        mut self,
+        args: Vec<TransactionArgument>,
+        deps: Vec<VerifiedModule>,
+    ) -> Result<Program> {
+        self.frozen_deps = None;
+        self.skip_stdlib_deps = true;
+        self.extra_deps = deps;
+        self.into_program(args)
+    }
+
+    /// Compiles the code and arguments into a `Program` -- the bytecode is serialized.
+    #[deprecated(
+        note = "`deps` is ignored, use `into_program` with `extra_deps` or `into_program_with_deps`"
+    )]
+    pub fn into_program_2(
+        self,
+        args: Vec<TransactionArgument>,
+        _deps: Vec<CompiledModule>,
+    ) -> Result<Program> {
+        self.into_program(args)
+    }
+
+    /// Compiles the code and returns the stdlib modules imported by its modules and script, rather
+    /// than the whole stdlib, e.g. to prune the dependencies published along with it.
+    pub fn used_stdlib_modules(self) -> Result<Vec<ModuleId>> {
+        let compiled_program = self.compile_impl()?.0;
+        let stdlib: BTreeSet<ModuleId> = self
+            .stdlib()
+            .iter()
+            .map(|module| module.self_id())
+            .collect();
+        let script = compiled_program.script.into_module();
+        let mut used = BTreeSet::new();
+        for module in compiled_program.modules.iter().chain(Some(&script)) {
+            for handle in module.module_handles() {
+                let module_id = module.module_id_for_handle(handle);
+                if stdlib.contains(&module_id) {
+                    used.insert(module_id);
+                }
+            }
+        }
+        Ok(used.into_iter().collect())
+    }
+
+    /// Compiles the code and runs the lint passes over its modules.
+    pub fn into_lint_diagnostics(self) -> Result<Vec<Diagnostic>> {
+        let parsed_program = self.parse()?;
+        let deps = self.deps()?;
+        let compiled_program = compile_program(&self.address, &parsed_program, deps.iter())
+            .map_err(|err| self.name_error(err))?;
+        Ok(parsed_program
+            .modules
+            .iter()
+            .zip(&compiled_program.modules)
+            .flat_map(|(source, module)| lint::public_resource_returns(source, module))
+            .collect())
     }
 
-    fn compile_impl(&mut self) -> Result<(CompiledProgram, Vec<VerifiedModule>)> {
-        let parsed_program = parse_program(self.code)?;
-        let deps = self.deps();
-        let compiled_program = compile_program(&self.address, &parsed_program, &deps)?;
+    fn parse(&self) -> Result<ast::Program> {
+        if self.ascii_identifiers_only {
+            checks::check_ascii_identifiers(self.code)?;
+        }
+        let code = if self.address_aliases.is_empty() {
+            Cow::Borrowed(self.code)
+        } else {
+            Cow::Owned(substitute_address_aliases(self.code, &self.address_aliases))
+        };
+        let program = match self.source_name {
+            Some(name) => parse_program_with_name(name, &code)?,
+            None => parse_program(&code)?,
+        };
+        checks::check_entry_functions_public(&program, &self.entry_functions)?;
+        checks::check_import_cycles(&program, &self.address)?;
+        Ok(program)
+    }
+
+    fn compile_impl(&self) -> Result<(CompiledProgram, Cow<[VerifiedModule]>)> {
+        let (compiled_program, _, deps) = self.compile_with_source_map()?;
         Ok((compiled_program, deps))
     }
 
-    fn compile_mod(&mut self) -> Result<(CompiledModule, Vec<VerifiedModule>)> {
-        let parsed_program = parse_program(self.code)?;
-        let deps = self.deps();
-        assert_eq!(parsed_program.modules.len(), 1, "Must have single module");
-        let module = parsed_program.modules.get(0).expect("Module must exist");
-        let compiled_module = compile_module(&self.address, module, &deps)?;
+    fn compile_with_source_map(
+        &self,
+    ) -> Result<(CompiledProgram, SourceMap, Cow<[VerifiedModule]>)> {
+        let deps = self.deps()?;
+        let (compiled_program, source_map) = self.compile_against(&deps)?;
+        Ok((compiled_program, source_map, deps))
+    }
+
+    fn compile_against(&self, deps: &[VerifiedModule]) -> Result<(CompiledProgram, SourceMap)> {
+        let parsed_program = self.parse()?;
+        let (compiled_program, source_map) =
+            compile_program_with_source_map(&self.address, &parsed_program, deps)
+                .map_err(|err| self.name_error(err))?;
+        self.verify(&compiled_program.modules)?;
+        self.verify(&[compiled_program.script.clone().into_module()])?;
+        checks::check_struct_fields(&compiled_program.modules, &self.expected_struct_fields)?;
+        if let Some(max) = self.max_type_params {
+            checks::check_max_type_params(&compiled_program.modules, max)?;
+        }
+        Ok((compiled_program, source_map))
+    }
+
+    fn compile_mod(&self) -> Result<(CompiledModule, Cow<[VerifiedModule]>)> {
+        let parsed_program = self.parse()?;
+        let deps = self.deps()?;
+        match parsed_program.modules.len() {
+            0 => return Err(self.name_error(format_err!("expected a module, but found none"))),
+            1 => (),
+            count => {
+                return Err(
+                    self.name_error(format_err!("expected a single module, but found {}", count))
+                )
+            }
+        }
+        let module = &parsed_program.modules[0];
+        let compiled_module = compile_module(&self.address, module, deps.iter())
+            .map_err(|err| self.name_error(err))?;
+        self.verify(std::slice::from_ref(&compiled_module))?;
+        checks::check_struct_fields(
+            std::slice::from_ref(&compiled_module),
+            &self.expected_struct_fields,
+        )?;
+        if let Some(max) = self.max_type_params {
+            checks::check_max_type_params(std::slice::from_ref(&compiled_module), max)?;
+        }
         Ok((compiled_module, deps))
     }
 
-    fn deps(&mut self) -> Vec<VerifiedModule> {
-        let extra_deps = mem::replace(&mut self.extra_deps, vec![]);
-        if self.skip_stdlib_deps {
-            extra_deps
-        } else {
-            let mut deps = stdlib_modules().to_vec();
-            deps.extend(extra_deps);
-            deps
+    /// Serializes each of `modules`, naming the module that fails to serialize, if any.
+    fn serialize_modules(&self, modules: &[CompiledModule]) -> Result<Vec<Vec<u8>>> {
+        let mut serialized_modules = vec![];
+        for (idx, module) in modules.iter().enumerate() {
+            let mut serialized_module = Vec::<u8>::new();
+            module
+                .serialize_with_version(&mut serialized_module, self.major_version())
+                .map_err(|err| {
+                    format_err!(
+                        "module {} ({}) can't be serialized: {}",
+                        idx,
+                        module.self_id().name(),
+                        err
+                    )
+                })?;
+            serialized_modules.push(serialized_module);
         }
+        Ok(serialized_modules)
     }
 
-    pub fn add_std_deps(&mut self,mut deps2:  Vec<VerifiedModule>) -> Vec<VerifiedModule> {
-        let extra_deps = mem::replace(&mut deps2, vec![]);
+    fn major_version(&self) -> u8 {
+        self.bytecode_version
+            .unwrap_or(BinaryConstants::VERSION_MAJOR)
+    }
 
-            let mut deps = stdlib_modules().to_vec();
-            deps.extend(extra_deps);
-            deps
+    /// Prefixes `err` with the name of the code, if it has one.
+    fn name_error(&self, err: Error) -> Error {
+        match self.source_name {
+            Some(name) => format_err!("{}: {}", name, err),
+            None => err,
+        }
+    }
+
+    fn verify(&self, modules: &[CompiledModule]) -> Result<()> {
+        let passes = match (self.verifier_passes, self.verify) {
+            (Some(passes), _) => passes,
+            (None, true) => VerifierPassSet::all(),
+            (None, false) => return Ok(()),
+        };
+        if !passes.is_all() {
+            warn!(
+                "[compiler] verifying with a reduced pass set {:?}, the output may be unsafe",
+                passes
+            );
+        }
+        for module in modules {
+            let errors = verify_module_with_passes(module, passes);
+            ensure!(
+                errors.is_empty(),
+                "{:?} failed verification: {:?}",
+                module.self_id(),
+                errors
+            );
+        }
+        Ok(())
     }
 
+    /// Returns `custom_stdlib` if it is set, the stdlib otherwise.
+    fn stdlib(&self) -> &[VerifiedModule] {
+        match &self.custom_stdlib {
+            Some(custom_stdlib) => custom_stdlib,
+            None => stdlib_modules(),
+        }
+    }
+
+    /// Returns the modules to compile against. `frozen_deps`, if set, take precedence over
+    /// everything else. Otherwise these are the stdlib, unless `skip_stdlib_deps` is set, followed
+    /// by `extra_deps`. The stdlib and `extra_deps` are borrowed rather than cloned unless both
+    /// are needed. The stdlib is built and verified once per process, by `stdlib_modules`, and
+    /// shared by every `Compiler`.
+    ///
+    /// If several of these modules have the same `ModuleId`, the last one listed wins, so an extra
+    /// dependency replaces the stdlib module it shares an id with.
+    fn deps(&self) -> Result<Cow<[VerifiedModule]>> {
+        if let Some(frozen_deps) = &self.frozen_deps {
+            return Ok(Cow::Owned(load_frozen_deps(frozen_deps)?));
+        }
+        let deps = if self.skip_stdlib_deps {
+            Cow::Borrowed(&self.extra_deps[..])
+        } else if self.extra_deps.is_empty() {
+            Cow::Borrowed(self.stdlib())
+        } else {
+            let mut deps = self.stdlib().to_vec();
+            deps.extend(self.extra_deps.iter().cloned());
+            Cow::Owned(deps)
+        };
+        Ok(dedup_by_module_id(deps))
+    }
+}
+
+/// Drops every module that is followed by another one with the same `ModuleId`. The remaining
+/// modules keep their relative order. `deps` is returned as is if there are no duplicates.
+fn dedup_by_module_id(deps: Cow<[VerifiedModule]>) -> Cow<[VerifiedModule]> {
+    let last_idx: BTreeMap<ModuleId, usize> = deps
+        .iter()
+        .enumerate()
+        .map(|(idx, module)| (module.self_id(), idx))
+        .collect();
+    if last_idx.len() == deps.len() {
+        return deps;
+    }
+    Cow::Owned(
+        deps.iter()
+            .enumerate()
+            .filter(|(idx, module)| last_idx[&module.self_id()] == *idx)
+            .map(|(_, module)| module.clone())
+            .collect(),
+    )
+}
+
+/// Compiles each of `sources` into a `CompiledScript` with the default options, sent from
+/// `address`. This is a convenience for compiling a batch of scripts: it costs the same as using a
+/// `Compiler` per script, as the stdlib is built once per process and every `Compiler` borrows
+/// it, see `Compiler::deps`.
+///
+/// The scripts are returned in the order of `sources`. Compilation stops at the first script that
+/// fails, and the error names its index.
+pub fn compile_scripts(address: AccountAddress, sources: &[&str]) -> Result<Vec<CompiledScript>> {
+    sources
+        .iter()
+        .enumerate()
+        .map(|(idx, code)| {
+            Compiler::new(address, code)
+                .into_script()
+                .map_err(|err| format_err!("script {} failed to compile: {}", idx, err))
+        })
+        .collect()
+}
+
+/// Deserializes and verifies a frozen dependency snapshot. Every module has to pass the bytecode
+/// verifier on its own and link against the modules listed before it.
+fn load_frozen_deps(blobs: &[Vec<u8>]) -> Result<Vec<VerifiedModule>> {
+    let mut deps: Vec<VerifiedModule> = vec![];
+    for (idx, blob) in blobs.iter().enumerate() {
+        let module = CompiledModule::deserialize(blob).map_err(|err| {
+            format_err!("frozen dependency {} can't be deserialized: {}", idx, err)
+        })?;
+        let module = VerifiedModule::new(module).map_err(|(_, errors)| {
+            format_err!(
+                "frozen dependency {} failed verification: {:?}",
+                idx,
+                errors
+            )
+        })?;
+        let errors = verify_module_dependencies(&module, &deps);
+        ensure!(
+            errors.is_empty(),
+            "frozen dependency {} ({:?}) doesn't link against the snapshot: {:?}",
+            idx,
+            module.self_id(),
+            errors
+        );
+        deps.push(module);
+    }
+    Ok(deps)
+}
+
+/// Replaces every alias in `aliases` that directly follows an `import` keyword and precedes a `.`
+/// with the address it stands for.
+fn substitute_address_aliases(code: &str, aliases: &BTreeMap<String, AccountAddress>) -> String {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut substituted = String::with_capacity(code.len());
+    let mut after_import = false;
+    let mut rest = code;
+    while let Some(start) = rest.find(is_ident_char) {
+        let (separator, tail) = rest.split_at(start);
+        let end = tail
+            .find(|c: char| !is_ident_char(c))
+            .unwrap_or_else(|| tail.len());
+        let (ident, tail) = tail.split_at(end);
+        substituted.push_str(separator);
+        match aliases.get(ident) {
+            Some(address)
+                if after_import && separator.trim().is_empty() && tail.starts_with('.') =>
+            {
+                // `AccountAddress` displays as bare hex, but address literals need the prefix.
+                substituted.push_str(&format!("0x{}", address))
+            }
+            _ => substituted.push_str(ident),
+        }
+        after_import = ident == "import";
+        rest = tail;
+    }
+    substituted.push_str(rest);
+    substituted
 }
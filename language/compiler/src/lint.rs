@@ -0,0 +1,78 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Lint passes run over compiled modules. Each pass reports its findings as `Diagnostic`s
+//! pointing back into the source.
+
+use crate::diagnostics::Diagnostic;
+use ir_to_bytecode::parser::ast;
+use vm::{
+    access::ModuleAccess,
+    file_format::{CompiledModule, Kind, SignatureToken},
+    views::FunctionDefinitionView,
+};
+
+/// Warns about every public function of `module` whose return type contains a resource, since
+/// any caller can use such a function to extract the resource. `source` is the AST `module` was
+/// compiled from and supplies the spans.
+pub fn public_resource_returns(
+    source: &ast::ModuleDefinition,
+    module: &CompiledModule,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for function_def in module.function_defs() {
+        let view = FunctionDefinitionView::new(module, function_def);
+        if !view.is_public() {
+            continue;
+        }
+        let handle = module.function_handle_at(function_def.function);
+        let signature = module.function_signature_at(handle.signature);
+        let returns_resource = signature
+            .return_types
+            .iter()
+            .any(|token| contains_resource(module, token, &signature.type_formals));
+        if !returns_resource {
+            continue;
+        }
+        let span = source
+            .functions
+            .iter()
+            .find(|(name, _)| name.name_ref() == view.name())
+            .map(|(_, function)| function.span)
+            .expect("compiled function must be declared in the source");
+        diagnostics.push(Diagnostic::warning(
+            format!(
+                "public function `{}.{}` returns a resource",
+                module.name(),
+                view.name()
+            ),
+            span,
+        ));
+    }
+    diagnostics
+}
+
+/// Returns whether a value of type `token` holds a resource by value. References don't count, as
+/// they can't move the resource out.
+fn contains_resource(
+    module: &CompiledModule,
+    token: &SignatureToken,
+    type_formals: &[Kind],
+) -> bool {
+    match token {
+        SignatureToken::Struct(idx, type_actuals) => {
+            module.struct_handle_at(*idx).is_nominal_resource
+                || type_actuals
+                    .iter()
+                    .any(|actual| contains_resource(module, actual, type_formals))
+        }
+        SignatureToken::TypeParameter(idx) => type_formals[*idx as usize] == Kind::Resource,
+        SignatureToken::Reference(_)
+        | SignatureToken::MutableReference(_)
+        | SignatureToken::Bool
+        | SignatureToken::U64
+        | SignatureToken::String
+        | SignatureToken::ByteArray
+        | SignatureToken::Address => false,
+    }
+}
@@ -132,13 +132,9 @@ fn main() {
 
     if !args.module_input {
         let source = fs::read_to_string(args.source_path).expect("Unable to read file");
-        let compiler = Compiler {
-            address,
-            code: &source,
-            skip_stdlib_deps: args.no_stdlib,
-            extra_deps: deps,
-            ..Compiler::default()
-        };
+        let mut compiler = Compiler::new(address, &source);
+        compiler.skip_stdlib_deps = args.no_stdlib;
+        compiler.extra_deps = deps;
         let (compiled_program, dependencies) = compiler
             .into_compiled_program_and_deps()
             .expect("Failed to compile program");
@@ -0,0 +1,75 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Compiling a sequence of modules and scripts, each against the modules published before it.
+
+use crate::Compiler;
+use bytecode_verifier::{verify_module_dependencies, VerifiedModule};
+use failure::prelude::*;
+use stdlib::stdlib_modules;
+use types::account_address::AccountAddress;
+use vm::{access::ModuleAccess, file_format::CompiledScript};
+
+/// Compiles code against the stdlib and every module published in the session so far, e.g. for
+/// REPL-style tools. Published modules are kept verified, so they are neither serialized nor
+/// verified again when later code is compiled against them.
+#[derive(Clone, Debug)]
+pub struct CompilationSession {
+    address: AccountAddress,
+    modules: Vec<VerifiedModule>,
+}
+
+impl CompilationSession {
+    /// Creates an empty session compiling with `address` as the sender.
+    pub fn new(address: AccountAddress) -> Self {
+        CompilationSession {
+            address,
+            modules: vec![],
+        }
+    }
+
+    /// Returns the modules published so far, in the order they were published.
+    pub fn modules(&self) -> &[VerifiedModule] {
+        &self.modules
+    }
+
+    /// Compiles the single module in `code` and publishes it, so that later code can import it.
+    ///
+    /// Fails if the module doesn't compile or verify, or if a module with the same name has
+    /// already been published.
+    pub fn publish_module(&mut self, code: &str) -> Result<()> {
+        let module = self.compiler(code).into_compiled_module()?;
+        ensure!(
+            self.modules
+                .iter()
+                .all(|published| published.self_id() != module.self_id()),
+            "module {:?} is already published",
+            module.self_id()
+        );
+        let module = VerifiedModule::new(module).map_err(|(module, errors)| {
+            format_err!("{:?} failed verification: {:?}", module.self_id(), errors)
+        })?;
+        let errors =
+            verify_module_dependencies(&module, stdlib_modules().iter().chain(&self.modules));
+        ensure!(
+            errors.is_empty(),
+            "{:?} doesn't link against its dependencies: {:?}",
+            module.self_id(),
+            errors
+        );
+        self.modules.push(module);
+        Ok(())
+    }
+
+    /// Compiles the script in `code` against the modules published so far.
+    pub fn compile_script(&self, code: &str) -> Result<CompiledScript> {
+        self.compiler(code).into_script()
+    }
+
+    fn compiler<'a>(&self, code: &'a str) -> Compiler<'a> {
+        Compiler {
+            extra_deps: self.modules.clone(),
+            ..Compiler::new(self.address, code)
+        }
+    }
+}
@@ -0,0 +1,1049 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{compile_scripts, Compiler, VerifierPassSet, VerifyProgramError};
+use bytecode_verifier::VerifiedModule;
+use std::collections::BTreeMap;
+use stdlib::stdlib_modules;
+use types::{
+    account_address::AccountAddress,
+    language_storage::ModuleId,
+    transaction::{TransactionArgument, TransactionPayload},
+};
+use vm::{
+    access::{ModuleAccess, ScriptAccess},
+    errors::VerificationStatus,
+    file_format::{CompiledModule, CompiledScript},
+    file_format_common::BinaryConstants,
+};
+
+fn compile_module_blob(code: &str) -> Vec<u8> {
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    compiler.into_module_blob().unwrap()
+}
+
+#[test]
+fn compile_against_frozen_deps() {
+    let dep = compile_module_blob(
+        "
+        module M {
+            public f() {
+                return;
+            }
+        }
+        ",
+    );
+    let code = "
+        import 0x0.M;
+
+        main() {
+            M.f();
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        frozen_deps: Some(vec![dep]),
+        ..Compiler::default()
+    };
+    let (_, deps) = compiler.into_compiled_program_and_deps().unwrap();
+    assert_eq!(deps.len(), 1);
+    assert_eq!(deps[0].name(), "M");
+}
+
+#[test]
+fn frozen_deps_bypass_stdlib() {
+    let dep = compile_module_blob(
+        "
+        module M {
+            public f() {
+                return;
+            }
+        }
+        ",
+    );
+    // LibraCoin is part of the live stdlib but not of the snapshot.
+    let code = "
+        import 0x0.LibraCoin;
+
+        main() {
+            let coin: LibraCoin.T;
+            coin = LibraCoin.zero();
+            LibraCoin.destroy_zero(move(coin));
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        frozen_deps: Some(vec![dep]),
+        ..Compiler::default()
+    };
+    assert!(compiler.into_compiled_program().is_err());
+}
+
+#[test]
+fn program_links_against_stdlib_and_extra_deps() {
+    let code = "
+        import 0x0.LibraCoin;
+        import 0x0.M;
+
+        main() {
+            let coin: LibraCoin.T;
+            coin = LibraCoin.zero();
+            LibraCoin.destroy_zero(move(coin));
+            M.f();
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        extra_deps: vec![verified_module("M")],
+        ..Compiler::default()
+    };
+    compiler.into_program(vec![]).unwrap();
+}
+
+#[test]
+fn program_links_against_explicit_deps_only() {
+    let code = "
+        import 0x0.M;
+
+        main() {
+            M.f();
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        extra_deps: vec![verified_module("N")],
+        ..Compiler::default()
+    };
+    compiler
+        .into_program_with_deps(vec![], vec![verified_module("M")])
+        .unwrap();
+
+    // The stdlib isn't linked in when the dependencies are given explicitly.
+    let code = "
+        import 0x0.LibraCoin;
+
+        main() {
+            let coin: LibraCoin.T;
+            coin = LibraCoin.zero();
+            LibraCoin.destroy_zero(move(coin));
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        ..Compiler::default()
+    };
+    assert!(compiler
+        .into_program_with_deps(vec![], vec![verified_module("M")])
+        .is_err());
+}
+
+#[test]
+fn list_used_stdlib_modules() {
+    let code = "
+        import 0x0.LibraCoin;
+
+        main() {
+            let coin: LibraCoin.T;
+            coin = LibraCoin.zero();
+            LibraCoin.destroy_zero(move(coin));
+            return;
+        }
+        ";
+    let used = Compiler::new(AccountAddress::default(), code)
+        .used_stdlib_modules()
+        .unwrap();
+    assert_eq!(used.len(), 1);
+    assert_eq!(used[0].name(), "LibraCoin");
+}
+
+#[test]
+fn frozen_deps_reject_malformed_blob() {
+    let compiler = Compiler {
+        code: "main() { return; }",
+        frozen_deps: Some(vec![vec![0xde, 0xad, 0xbe, 0xef]]),
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_program().unwrap_err();
+    assert!(err.to_string().contains("frozen dependency 0"));
+}
+
+#[test]
+fn reject_non_ascii_identifiers() {
+    let code = "
+        module M {
+            public naïve() {
+                return;
+            }
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ascii_identifiers_only: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_module().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "non-ASCII identifier `naïve` at line 3, column 20"
+    );
+}
+
+#[test]
+fn ascii_identifiers_accepted() {
+    let compiler = Compiler {
+        code: "module M { public naive() { return; } } // comments may say naïve",
+        skip_stdlib_deps: true,
+        ascii_identifiers_only: true,
+        ..Compiler::default()
+    };
+    compiler.into_compiled_module().unwrap();
+}
+
+#[test]
+fn bind_script_args_by_name() {
+    let code = "
+        main(amount: u64, payee: address) {
+            return;
+        }
+        ";
+    let payee = AccountAddress::new([1u8; 32]);
+    let compile = |args: BTreeMap<String, TransactionArgument>| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            ..Compiler::default()
+        };
+        compiler.into_program_named(args)
+    };
+
+    let mut args = BTreeMap::new();
+    args.insert("payee".to_string(), TransactionArgument::Address(payee));
+    args.insert("amount".to_string(), TransactionArgument::U64(10));
+    let program = compile(args.clone()).unwrap();
+    assert_eq!(
+        program.args(),
+        &[
+            TransactionArgument::U64(10),
+            TransactionArgument::Address(payee)
+        ][..]
+    );
+
+    let mut missing = args.clone();
+    missing.remove("amount");
+    let err = compile(missing).unwrap_err();
+    assert_eq!(err.to_string(), "missing argument `amount`");
+
+    let mut extra = args;
+    extra.insert("memo".to_string(), TransactionArgument::U64(0));
+    let err = compile(extra).unwrap_err();
+    assert_eq!(err.to_string(), "unknown arguments: [\"memo\"]");
+}
+
+#[test]
+fn validate_script_args() {
+    let code = "
+        main(amount: u64, payee: address) {
+            return;
+        }
+        ";
+    let compile = |args: Vec<TransactionArgument>| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            validate_args: true,
+            ..Compiler::default()
+        };
+        compiler.into_program(args)
+    };
+
+    let payee = TransactionArgument::Address(AccountAddress::new([1u8; 32]));
+    compile(vec![TransactionArgument::U64(10), payee.clone()]).unwrap();
+
+    let err = compile(vec![TransactionArgument::U64(10)]).unwrap_err();
+    assert_eq!(err.to_string(), "script expects 2 arguments, got 1");
+
+    let err = compile(vec![payee, TransactionArgument::U64(10)]).unwrap_err();
+    assert!(err.to_string().starts_with("argument 0 is"));
+}
+
+#[test]
+fn resolve_address_aliases_in_imports() {
+    let std_address = AccountAddress::new([1u8; 32]);
+    let dep = Compiler {
+        address: std_address,
+        code: "
+            module M {
+                public f() {
+                    return;
+                }
+            }
+            ",
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    }
+    .into_compiled_module()
+    .unwrap();
+    let dep = VerifiedModule::new(dep).unwrap();
+
+    let mut address_aliases = BTreeMap::new();
+    address_aliases.insert("Std".to_string(), std_address);
+    let compiler = Compiler {
+        code: "
+            import Std.M;
+
+            main() {
+                M.f();
+                return;
+            }
+            ",
+        skip_stdlib_deps: true,
+        extra_deps: vec![dep],
+        address_aliases,
+        ..Compiler::default()
+    };
+    let script = compiler
+        .into_compiled_program()
+        .unwrap()
+        .script
+        .into_module();
+    let import = &script.module_handles()[1];
+    assert_eq!(
+        script.module_id_for_handle(import),
+        ModuleId::new(std_address, "M".to_string())
+    );
+}
+
+#[test]
+fn verify_and_skip_stdlib_deps_are_independent() {
+    // The module fails verification, and nothing needs the stdlib to compile.
+    let code = "
+        modules:
+        module M {
+            public f(): &u64 {
+                let x: u64;
+                x = 1;
+                return &x;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compile = |skip_stdlib_deps, verify| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps,
+            verify,
+            ..Compiler::default()
+        };
+        compiler
+            .into_compiled_program_and_deps()
+            .map_err(|err| err.to_string())
+    };
+
+    for skip_stdlib_deps in &[false, true] {
+        let (_, deps) = compile(*skip_stdlib_deps, false).unwrap();
+        assert_eq!(deps.is_empty(), *skip_stdlib_deps);
+        assert!(compile(*skip_stdlib_deps, true)
+            .unwrap_err()
+            .contains("failed verification"));
+    }
+}
+
+#[test]
+fn verify_with_selected_passes() {
+    // Returning a reference to a local is only caught by the reference safety analysis.
+    let code = "
+        module M {
+            public f(): &u64 {
+                let x: u64;
+                x = 1;
+                return &x;
+            }
+        }
+        ";
+    let compile = |verifier_passes| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            verifier_passes,
+            ..Compiler::default()
+        };
+        compiler.into_module_blob()
+    };
+
+    let err = compile(Some(VerifierPassSet::all())).unwrap_err();
+    assert!(err.to_string().contains("failed verification"));
+
+    let reduced = VerifierPassSet {
+        type_and_memory_safety: false,
+        ..VerifierPassSet::all()
+    };
+    assert!(!reduced.is_all());
+    let blob = compile(Some(reduced)).unwrap();
+    assert_eq!(blob, compile(None).unwrap());
+}
+
+#[test]
+fn reject_private_entry_functions() {
+    let code = "
+        module M {
+            public start() {
+                return;
+            }
+            run() {
+                return;
+            }
+        }
+        ";
+    let compile = |entry_function: &str| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            entry_functions: vec![entry_function.to_string()],
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module()
+    };
+
+    compile("M.start").unwrap();
+    let err = compile("M.run").unwrap_err();
+    assert_eq!(err.to_string(), "entry function `M.run` must be public");
+    let err = compile("M.strat").unwrap_err();
+    assert_eq!(err.to_string(), "entry function `M.strat` isn't defined");
+    let err = compile("start").unwrap_err();
+    assert_eq!(err.to_string(), "entry function `start` isn't defined");
+}
+
+#[test]
+fn check_expected_struct_fields() {
+    let compile = |code, fields: &[&str]| {
+        let mut expected_struct_fields = BTreeMap::new();
+        expected_struct_fields.insert(
+            "M.S".to_string(),
+            fields.iter().map(|field| field.to_string()).collect(),
+        );
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            expected_struct_fields,
+            ..Compiler::default()
+        };
+        compiler.into_module_blob()
+    };
+    let declared = "
+        module M {
+            struct S { flag: bool, amount: u64 }
+        }
+        ";
+    let reordered = "
+        module M {
+            struct S { amount: u64, flag: bool }
+        }
+        ";
+
+    // Reordering fields in the source compiles to the same layout, so only the set is checked.
+    let blob = compile(declared, &["amount", "flag"]).unwrap();
+    assert_eq!(blob, compile(reordered, &["flag", "amount"]).unwrap());
+
+    let err = compile(declared, &["amount", "flags"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "struct `M.S` has fields {\"amount\", \"flag\"}, expected {\"amount\", \"flags\"}"
+    );
+    let err = compile(declared, &["amount"]).unwrap_err();
+    assert!(err.to_string().starts_with("struct `M.S` has fields "));
+}
+
+#[test]
+fn reject_cyclic_module_imports() {
+    let code = "
+        modules:
+        module A {
+            import Transaction.B;
+            public f() {
+                return;
+            }
+        }
+        module B {
+            import 0x0.C;
+            public g() {
+                return;
+            }
+        }
+        module C {
+            import Transaction.A;
+            public h() {
+                return;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let err = compiler.into_compiled_program().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cyclic module dependency: A -> B -> C -> A"
+    );
+}
+
+#[test]
+fn reject_functions_over_type_param_limit() {
+    let code = "
+        module M {
+            public one<T>(x: u64) {
+                return;
+            }
+
+            public three<A, B, C>(x: u64) {
+                return;
+            }
+        }
+        ";
+    let compile = |max_type_params| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            max_type_params,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_module()
+    };
+
+    compile(None).unwrap();
+    compile(Some(3)).unwrap();
+    let err = compile(Some(2)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function `M.three` has 3 type parameters, the maximum is 2"
+    );
+}
+
+fn verified_module(name: &str) -> VerifiedModule {
+    let code = format!(
+        "
+        module {} {{
+            public f() {{
+                return;
+            }}
+        }}
+        ",
+        name
+    );
+    let module = Compiler {
+        code: &code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    }
+    .into_compiled_module()
+    .unwrap();
+    VerifiedModule::new(module).unwrap()
+}
+
+#[test]
+fn deps_keeps_extra_deps() {
+    let module = verified_module("M");
+    let module_id = module.self_id();
+    let compiler = Compiler {
+        skip_stdlib_deps: true,
+        extra_deps: vec![module],
+        ..Compiler::default()
+    };
+
+    for _ in 0..2 {
+        let deps = compiler.deps().unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].self_id(), module_id);
+    }
+}
+
+#[test]
+fn extra_deps_replace_stdlib_modules_with_the_same_id() {
+    let stdlib_id = stdlib_modules()[0].self_id();
+    let replacement = verified_module(stdlib_id.name());
+    assert_eq!(replacement.self_id(), stdlib_id);
+    let compiler = Compiler {
+        extra_deps: vec![replacement.clone()],
+        ..Compiler::default()
+    };
+
+    let deps = compiler.deps().unwrap();
+    assert_eq!(deps.len(), stdlib_modules().len());
+    let kept: Vec<_> = deps
+        .iter()
+        .filter(|dep| dep.self_id() == stdlib_id)
+        .collect();
+    assert_eq!(kept, vec![&replacement]);
+}
+
+#[test]
+fn custom_stdlib_replaces_stdlib() {
+    let custom = verified_module("Custom");
+    let compiler = Compiler {
+        custom_stdlib: Some(vec![custom.clone()]),
+        ..Compiler::default()
+    };
+
+    let deps = compiler.deps().unwrap();
+    assert_eq!(deps.to_vec(), vec![custom]);
+    for module in stdlib_modules() {
+        assert!(deps.iter().all(|dep| dep.self_id() != module.self_id()));
+    }
+}
+
+#[test]
+fn script_deps_include_stdlib() {
+    let (script, deps) = Compiler::new(AccountAddress::default(), "main() { return; }")
+        .into_script_and_deps()
+        .unwrap();
+    assert!(!script.as_inner().function_defs.is_empty());
+    for module in stdlib_modules() {
+        assert!(deps.iter().any(|dep| dep.self_id() == module.self_id()));
+    }
+}
+
+#[test]
+fn add_deps_appends_and_set_deps_replaces() {
+    let mut compiler = Compiler {
+        extra_deps: vec![verified_module("A")],
+        ..Compiler::default()
+    };
+    compiler.add_deps(vec![verified_module("B"), verified_module("C")]);
+    assert_eq!(compiler.extra_deps.len(), 3);
+
+    compiler.set_deps(vec![verified_module("D")]);
+    assert_eq!(compiler.extra_deps.len(), 1);
+    assert_eq!(compiler.extra_deps[0].self_id().name(), "D");
+}
+
+#[test]
+fn add_dep_blobs_links_serialized_modules() {
+    let blob = Compiler {
+        code: "
+        module A {
+            public f() {
+                return;
+            }
+        }
+        ",
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    }
+    .into_module_blob()
+    .unwrap();
+
+    let mut compiler = Compiler {
+        code: "
+        import 0x0.A;
+        main() {
+            A.f();
+            return;
+        }
+        ",
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    compiler.add_dep_blobs(vec![blob]).unwrap();
+    assert_eq!(compiler.extra_deps.len(), 1);
+    compiler.into_compiled_program().unwrap();
+
+    let mut compiler = Compiler::default();
+    let err = compiler.add_dep_blobs(vec![vec![0xff]]).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("dependency 0 can't be deserialized"));
+    assert!(compiler.extra_deps.is_empty());
+}
+
+#[test]
+fn module_only_program_gets_no_op_script() {
+    let module = "
+        module M {
+            public f() {
+                return;
+            }
+        }
+        ";
+    let compile = |code: &str| {
+        Compiler {
+            code,
+            skip_stdlib_deps: true,
+            ..Compiler::default()
+        }
+        .into_program(vec![])
+        .unwrap()
+    };
+
+    let program = compile(module);
+    let explicit = compile(&format!("modules:{}script: main() {{ return; }}", module));
+    assert_eq!(program.modules().len(), 1);
+    assert_eq!(program.modules(), explicit.modules());
+    assert_eq!(program.code(), explicit.code());
+}
+
+#[test]
+fn serialize_every_module_of_a_program() {
+    let code = "
+        modules:
+        module A {
+            public f() {
+                return;
+            }
+        }
+        module B {
+            public g() {
+                return;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let blobs = compiler.into_module_blobs().unwrap();
+
+    assert_eq!(blobs.len(), 2);
+    let names: Vec<_> = blobs
+        .iter()
+        .map(|blob| {
+            assert!(!blob.is_empty());
+            CompiledModule::deserialize(blob)
+                .unwrap()
+                .self_id()
+                .name()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(names, vec!["A", "B"]);
+}
+
+#[test]
+fn source_map_points_at_statements() {
+    let code = "
+        main() {
+            let x: u64;
+            x = 1;
+            if (copy(x) == 1) {
+                x = 2;
+            }
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let (program, source_map) = compiler.into_compiled_program_and_source_map().unwrap();
+
+    assert!(source_map.modules.is_empty());
+    let statements: Vec<_> = source_map
+        .script
+        .iter()
+        .map(|(_, span)| &code[span.start().0 as usize..span.end().0 as usize])
+        .collect();
+    assert_eq!(statements, vec!["x = 1", "copy(x) == 1", "x = 2", "return"]);
+
+    let offsets: Vec<_> = source_map
+        .script
+        .iter()
+        .map(|(offset, _)| *offset)
+        .collect();
+    assert_eq!(offsets[0], 0);
+    assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+    let code_len = program.script.as_inner().main.code.code.len() as u16;
+    assert!(offsets.iter().all(|offset| *offset < code_len));
+}
+
+#[test]
+fn verify_program_collects_every_error() {
+    let verify = |code: &str| {
+        let compiler = Compiler {
+            code,
+            skip_stdlib_deps: true,
+            ..Compiler::default()
+        };
+        compiler.into_verified_program()
+    };
+
+    let (modules, _) = verify(
+        "
+        modules:
+        module M {
+            public f(): u64 {
+                return 1;
+            }
+        }
+        script:
+        import 0x0.M;
+
+        main() {
+            let x: u64;
+            x = M.f();
+            return;
+        }
+        ",
+    )
+    .unwrap();
+    assert_eq!(modules.len(), 1);
+
+    match verify("main() { x = 1; return; }") {
+        Err(VerifyProgramError::Compile(err)) => {
+            assert_eq!(err.to_string(), "variable x undefined")
+        }
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+
+    // Both the module and the script return or store a value of the wrong type.
+    let statuses = match verify(
+        "
+        modules:
+        module M {
+            public f(): u64 {
+                return true;
+            }
+        }
+        script:
+        main() {
+            let x: u64;
+            x = false;
+            return;
+        }
+        ",
+    ) {
+        Err(VerifyProgramError::Verify(statuses)) => statuses,
+        result => panic!("expected verification errors, got {:?}", result),
+    };
+    assert!(statuses.iter().any(|status| match status {
+        VerificationStatus::Module(0, _) => true,
+        _ => false,
+    }));
+    assert!(statuses.iter().any(|status| match status {
+        VerificationStatus::Script(_) => true,
+        _ => false,
+    }));
+}
+
+#[test]
+fn verify_program_collects_errors_despite_verifier_passes() {
+    // Both modules fail verification, so verifying while compiling would stop at the first one.
+    let code = "
+        modules:
+        module M {
+            public f(): u64 {
+                return true;
+            }
+        }
+        module N {
+            public g(): bool {
+                return 1;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        verifier_passes: Some(VerifierPassSet::all()),
+        ..Compiler::default()
+    };
+    let statuses = match compiler.into_verified_program() {
+        Err(VerifyProgramError::Verify(statuses)) => statuses,
+        result => panic!("expected verification errors, got {:?}", result),
+    };
+    for expected_idx in &[0, 1] {
+        assert!(statuses.iter().any(|status| match status {
+            VerificationStatus::Module(idx, _) => idx == expected_idx,
+            _ => false,
+        }));
+    }
+}
+
+#[test]
+fn compile_scripts_in_input_order() {
+    let sources = [
+        "main() { return; }",
+        "main(x: u64) { return; }",
+        "main(x: u64, y: bool) { return; }",
+    ];
+    let scripts = compile_scripts(AccountAddress::default(), &sources).unwrap();
+    assert_eq!(scripts.len(), 3);
+    for (idx, script) in scripts.iter().enumerate() {
+        let handle = script.function_handle_at(script.main().function);
+        assert_eq!(
+            script
+                .function_signature_at(handle.signature)
+                .arg_types
+                .len(),
+            idx
+        );
+    }
+
+    let sources = [
+        "main() { return; }",
+        "main() { retrun; }",
+        "main() { x = 1; }",
+    ];
+    let err = compile_scripts(AccountAddress::default(), &sources).unwrap_err();
+    assert!(err.to_string().starts_with("script 1 failed to compile: "));
+}
+
+#[test]
+fn dump_program_as_json() {
+    let code = "
+        modules:
+        module M {
+            public f(x: u64): u64 {
+                return move(x);
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let json = Compiler::new(AccountAddress::default(), code)
+        .into_json()
+        .unwrap();
+    let dump: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert!(dump["script"]["function_defs"].is_array());
+    assert_eq!(dump["modules"].as_array().unwrap().len(), 1);
+    let module = &dump["modules"][0];
+    assert_eq!(module["function_defs"][0]["name"], "f");
+    assert_eq!(module["function_handles"][0]["module"], "M");
+    assert!(module["string_pool"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|string| string == "f"));
+}
+
+#[test]
+fn wrap_programs_in_transaction_payloads() {
+    let args = vec![TransactionArgument::U64(7)];
+    let payload = Compiler::new(AccountAddress::default(), "main(x: u64) { return; }")
+        .into_transaction_payload(args.clone())
+        .unwrap();
+    match payload {
+        TransactionPayload::Script(script) => assert_eq!(script.args(), args.as_slice()),
+        payload => panic!("expected a script payload, got {:?}", payload),
+    }
+
+    let code = "
+        modules:
+        module M {
+            public f() { return; }
+        }
+        script:
+        main(x: u64) {
+            return;
+        }
+        ";
+    let payload = Compiler::new(AccountAddress::default(), code)
+        .into_transaction_payload(args.clone())
+        .unwrap();
+    match payload {
+        TransactionPayload::Program(program) => {
+            assert_eq!(program.args(), args.as_slice());
+            assert_eq!(program.modules().len(), 1);
+        }
+        payload => panic!("expected a program payload, got {:?}", payload),
+    }
+}
+
+#[test]
+fn serialize_with_bytecode_version() {
+    let code = "main() { return; }";
+    let current = Compiler::new(AccountAddress::default(), code)
+        .into_script_blob()
+        .unwrap();
+    let mut compiler = Compiler::new(AccountAddress::default(), code);
+    compiler.bytecode_version = Some(BinaryConstants::VERSION_MAJOR + 1);
+    let next = compiler.into_script_blob().unwrap();
+
+    let version_idx = BinaryConstants::LIBRA_MAGIC_SIZE;
+    assert_eq!(current[version_idx], BinaryConstants::VERSION_MAJOR);
+    assert_eq!(next[version_idx], BinaryConstants::VERSION_MAJOR + 1);
+    assert_eq!(current[..version_idx], next[..version_idx]);
+    assert_eq!(current[version_idx + 1..], next[version_idx + 1..]);
+    assert!(CompiledScript::deserialize(&current).is_ok());
+    assert!(CompiledScript::deserialize(&next).is_err());
+}
+
+#[test]
+fn compile_module_requires_a_single_module() {
+    let err = Compiler::new(AccountAddress::default(), "main() { return; }")
+        .into_compiled_module()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected a module, but found none");
+
+    let code = "
+        modules:
+        module A {
+            public f() { return; }
+        }
+        module B {
+            public g() { return; }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let err = Compiler::new(AccountAddress::default(), code)
+        .into_compiled_module()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected a single module, but found 2");
+}
+
+#[test]
+fn program_modules_deserialize_to_compiled_modules() {
+    let code = "
+        modules:
+        module A {
+            public f() { return; }
+        }
+        module B {
+            public g() { return; }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let (program, _, modules) = Compiler::new(AccountAddress::default(), code)
+        .into_program_and_deps(vec![])
+        .unwrap();
+    let deserialized: Vec<_> = program
+        .modules()
+        .iter()
+        .map(|blob| CompiledModule::deserialize(blob).unwrap())
+        .collect();
+    assert_eq!(deserialized, modules);
+}
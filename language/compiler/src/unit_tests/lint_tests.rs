@@ -0,0 +1,53 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{diagnostics::Severity, Compiler};
+
+#[test]
+fn public_functions_returning_resources_warn() {
+    let code = "
+        modules:
+        module M {
+            import 0x0.LibraCoin;
+
+            resource T { value: u64 }
+
+            public make(): Self.T {
+                return T{value: 1};
+            }
+            make_private(): Self.T {
+                return T{value: 2};
+            }
+            public count(): u64 {
+                return 3;
+            }
+            public zero(): LibraCoin.T {
+                return LibraCoin.zero();
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        ..Compiler::default()
+    };
+    let diagnostics = compiler.into_lint_diagnostics().unwrap();
+
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "public function `M.make` returns a resource",
+            "public function `M.zero` returns a resource",
+        ]
+    );
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+
+    let span = diagnostics[0].span;
+    let declaration = &code[span.start().0 as usize..span.end().0 as usize];
+    assert!(declaration.starts_with("public make(): Self.T {"));
+    assert!(declaration.ends_with('}'));
+}
@@ -7,8 +7,13 @@ pub(crate) mod testutils;
 
 mod branch_tests;
 mod cfg_tests;
+mod compiler_tests;
 mod expression_tests;
 mod function_tests;
 mod import_tests;
+mod lint_tests;
 mod serializer_tests;
+mod session_tests;
 mod stdlib_scripts;
+mod util_tests;
+mod watch_tests;
@@ -0,0 +1,37 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::session::CompilationSession;
+use types::account_address::AccountAddress;
+
+#[test]
+fn compile_script_against_published_module() {
+    let mut session = CompilationSession::new(AccountAddress::default());
+    let module = "
+        module M {
+            public f(): u64 {
+                return 7;
+            }
+        }
+        ";
+    session.publish_module(module).unwrap();
+    assert_eq!(session.modules().len(), 1);
+
+    session
+        .compile_script(
+            "
+            import 0x0.M;
+
+            main() {
+                let x: u64;
+                x = M.f();
+                return;
+            }
+            ",
+        )
+        .unwrap();
+
+    let err = session.publish_module(module).unwrap_err();
+    assert!(err.to_string().contains("already published"));
+    assert_eq!(session.modules().len(), 1);
+}
@@ -0,0 +1,640 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    util::{self, AddressKind},
+    Compiler,
+};
+use bytecode_verifier::VerifiedModule;
+use std::fs;
+use types::{
+    account_address::AccountAddress, language_storage::StructTag, transaction::TransactionArgument,
+};
+use vm::{
+    access::ModuleAccess,
+    file_format::{CompiledProgram, FunctionDefinitionIndex, StructDefinitionIndex},
+};
+
+#[test]
+fn symbol_table_lists_module_definitions() {
+    let code = "
+        modules:
+        module M {
+            struct S { value: u64 }
+            struct T { flag: bool }
+
+            public f(): u64 {
+                return 1;
+            }
+
+            public g(): bool {
+                return true;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let program = compiler.into_compiled_program().unwrap();
+    let table = util::symbol_table(&program);
+
+    assert_eq!(table.modules.len(), 1);
+    let module = &table.modules[0];
+    assert_eq!(module.id.name(), "M");
+    assert_eq!(module.function("f"), Some(FunctionDefinitionIndex::new(0)));
+    assert_eq!(module.function("g"), Some(FunctionDefinitionIndex::new(1)));
+    assert_eq!(module.struct_def("S"), Some(StructDefinitionIndex::new(0)));
+    assert_eq!(module.struct_def("T"), Some(StructDefinitionIndex::new(1)));
+    assert!(module.strings.iter().any(|(s, _)| s == "value"));
+
+    assert_eq!(
+        table.script.function("main"),
+        Some(FunctionDefinitionIndex::new(0))
+    );
+}
+
+#[test]
+fn minimize_failure_keeps_broken_function() {
+    let code = "
+        module M {
+            public f(): u64 {
+                return 1;
+            }
+
+            public broken() {
+                y = 1;
+                return;
+            }
+
+            public g(): bool {
+                return true;
+            }
+        }
+        ";
+    let minimized = util::minimize_failure(&AccountAddress::default(), code).unwrap();
+    assert!(minimized.contains("broken()"));
+    assert!(minimized.contains("y = 1;"));
+    assert!(!minimized.contains("f()"));
+    assert!(!minimized.contains("g()"));
+    assert!(minimized.lines().count() < code.lines().count());
+}
+
+#[test]
+fn minimize_failure_ignores_valid_programs() {
+    assert_eq!(
+        util::minimize_failure(&AccountAddress::default(), "main() { return; }"),
+        None
+    );
+}
+
+#[test]
+fn dependency_graph_dot_has_import_edges() {
+    let code = "
+        modules:
+        module A {
+            public a() {
+                return;
+            }
+        }
+        module B {
+            import 0x0.A;
+
+            public b() {
+                A.a();
+                return;
+            }
+        }
+        script:
+        import 0x0.A;
+        import 0x0.B;
+
+        main() {
+            A.a();
+            B.b();
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let (program, deps) = compiler.into_compiled_program_and_deps().unwrap();
+    let dot = util::dependency_graph_dot(&program, &deps);
+
+    assert!(dot.starts_with("digraph dependencies {\n"));
+    assert!(dot.contains("    \"script\" -> \"00000000.A\";\n"));
+    assert!(dot.contains("    \"script\" -> \"00000000.B\";\n"));
+    assert!(dot.contains("    \"00000000.B\" -> \"00000000.A\";\n"));
+    assert!(!dot.contains("\"00000000.A\" ->"));
+    assert_eq!(dot.matches(" -> ").count(), 3);
+}
+
+#[test]
+fn annotated_hex_dump_labels_tables() {
+    let code = "
+        module M {
+            resource T { value: u64 }
+
+            public f(): u64 {
+                return 1;
+            }
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let module = compiler.into_compiled_module().unwrap();
+    let dump = util::annotated_hex_dump(&module);
+
+    assert!(dump.starts_with("// header\n00000000: 4c 49 42 52 41 56 4d 0a"));
+    for label in &[
+        "// table directory\n",
+        "// module handles table\n",
+        "// struct handles table\n",
+        "// function handles table\n",
+        "// string pool table\n",
+        "// struct definitions table\n",
+        "// field definitions table\n",
+        "// function definitions table\n",
+    ] {
+        assert!(dump.contains(label), "missing {:?} in\n{}", label, dump);
+    }
+}
+
+#[test]
+fn compile_against_interface_file() {
+    let dep = Compiler {
+        code: "
+            module Coin {
+                resource T { value: u64 }
+
+                public mint(value: u64): Self.T {
+                    return T{value: move(value)};
+                }
+                public value(coin: &Self.T): u64 {
+                    return *&move(coin).value;
+                }
+                public burn(coin: Self.T) {
+                    let value: u64;
+                    T { value } = move(coin);
+                    return;
+                }
+            }
+            ",
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    }
+    .into_compiled_module()
+    .unwrap();
+    let interface = util::emit_interface(&dep);
+    let json = serde_json::to_string(&interface).unwrap();
+    let interface: util::InterfaceFile = serde_json::from_str(&json).unwrap();
+
+    let code = "
+        import 0x0.Coin;
+
+        main() {
+            let coin: Coin.T;
+            coin = Coin.mint(10);
+            assert(Coin.value(&coin) == 10, 42);
+            Coin.burn(move(coin));
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let compiler = compiler.with_interfaces(vec![interface]).unwrap();
+    compiler.into_compiled_program().unwrap();
+}
+
+#[test]
+fn compile_dir_report_lists_every_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("good.mvir"),
+        "
+        main() {
+            return;
+        }
+        ",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("broken.mvir"),
+        "
+        main() {
+            return
+        }
+        ",
+    )
+    .unwrap();
+    fs::write(dir.path().join("notes.txt"), "not Move IR").unwrap();
+
+    let report = util::compile_dir_report(dir.path(), &AccountAddress::default()).unwrap();
+    assert!(!report.is_success());
+    assert_eq!(report.files.len(), 2);
+    let (broken, good) = (&report.files[0], &report.files[1]);
+    assert!(broken.path.ends_with("broken.mvir"));
+    assert!(!broken.success);
+    assert_eq!(broken.diagnostics.len(), 1);
+    assert!(good.path.ends_with("good.mvir"));
+    assert!(good.success);
+    assert!(good.diagnostics.is_empty());
+
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(
+        serde_json::from_str::<util::CompileReport>(&json).unwrap(),
+        report
+    );
+}
+
+#[test]
+fn module_stream_roundtrip() {
+    let code = "
+        modules:
+        module A {
+            public f() {
+                return;
+            }
+        }
+        module B {
+            public g() {
+                return;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = || Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let mut stream = vec![];
+    compiler().serialize_modules_to(&mut stream).unwrap();
+
+    let modules = util::read_module_stream(&mut stream.as_slice()).unwrap();
+    assert_eq!(modules, compiler().into_compiled_program().unwrap().modules);
+
+    // A stream cut off within a module is an error.
+    stream.pop();
+    assert!(util::read_module_stream(&mut stream.as_slice()).is_err());
+}
+
+#[test]
+fn framed_programs_roundtrip() {
+    let code = "
+        main(x: u64) {
+            return;
+        }
+        ";
+    let arg_sets = vec![
+        vec![TransactionArgument::U64(1)],
+        vec![TransactionArgument::U64(2)],
+    ];
+    let frames = Compiler::new(AccountAddress::default(), code)
+        .into_framed_programs(arg_sets.clone())
+        .unwrap();
+
+    let programs = util::read_framed_programs(&frames).unwrap();
+    assert_eq!(programs.len(), 2);
+    for (program, args) in programs.iter().zip(&arg_sets) {
+        assert_eq!(program.args(), args.as_slice());
+        assert_eq!(program.code(), programs[0].code());
+    }
+
+    // Frames cut off within a program are an error.
+    assert!(util::read_framed_programs(&frames[..frames.len() - 1]).is_err());
+}
+
+#[test]
+fn source_file_errors_name_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("foo.mvir");
+    fs::write(&path, "main() {\n    return\n}\n").unwrap();
+
+    let source = util::SourceFile::read(&path).unwrap();
+    let err = source
+        .compiler(AccountAddress::default())
+        .into_compiled_program()
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with(&format!("{}:3: ", path.display())));
+
+    assert!(util::SourceFile::read(&dir.path().join("missing.mvir")).is_err());
+}
+
+#[test]
+fn emit_test_harness_uses_compile_helpers() {
+    let module = "
+        module M {
+            public f(x: u64) {
+                return;
+            }
+        }
+        ";
+    let importing_module = "
+        module N {
+            import 0x0.M;
+
+            public g(x: u64) {
+                M.f(move(x));
+                return;
+            }
+        }
+        ";
+    let script = "
+        import 0x0.N;
+
+        main(amount: u64, payee: address) {
+            N.g(move(amount));
+            return;
+        }
+        ";
+    let code = format!(
+        "modules:\n{}\n{}\nscript:\n{}",
+        module, importing_module, script
+    );
+    let program = Compiler::new(AccountAddress::default(), &code)
+        .into_compiled_program()
+        .unwrap();
+    let args = vec![
+        TransactionArgument::U64(7),
+        TransactionArgument::Address(AccountAddress::new([1u8; 32])),
+    ];
+    let harness =
+        util::emit_test_harness(&program, &[module, importing_module], script, &args).unwrap();
+
+    assert!(harness.contains("compile::{compile_module_with_deps, compile_script_with_deps},"));
+    // Each module is compiled against the ones before it, so N can import M.
+    assert!(
+        harness.contains("let blob = compile_module_with_deps(address, code, modules.clone());")
+    );
+    let zero_address = vec!["0x00"; 32].join(", ");
+    assert!(harness.contains(&format!(
+        "        (AccountAddress::new([{zero}]), MODULE_M),\n        (AccountAddress::new([{zero}]), MODULE_N),\n",
+        zero = zero_address
+    )));
+    assert!(harness.contains(&format!("const MODULE_M: &str = {:?};", module)));
+    assert!(harness.contains(&format!("const MODULE_N: &str = {:?};", importing_module)));
+    assert!(harness.contains(&format!("const SCRIPT: &str = {:?};", script)));
+    assert!(harness
+        .contains("let script = compile_script_with_deps(sender.address(), SCRIPT, modules);"));
+    assert!(!harness.contains("compile_script_with_address"));
+    assert!(!harness.contains("byte_array::ByteArray"));
+    assert!(harness.contains(&format!(
+        "TransactionArgument::U64(7), TransactionArgument::Address(AccountAddress::new([{}]))",
+        vec!["0x01"; 32].join(", ")
+    )));
+
+    // Without modules, the script is compiled on its own.
+    let script = "main() { return; }";
+    let program = Compiler::new(AccountAddress::default(), script)
+        .into_compiled_program()
+        .unwrap();
+    let harness = util::emit_test_harness(&program, &[], script, &[]).unwrap();
+    assert!(harness.contains("    compile::compile_script_with_address,\n"));
+    assert!(harness.contains("let script = compile_script_with_address(sender.address(), SCRIPT);"));
+    assert!(!harness.contains("compile_module_with_deps"));
+    assert!(!harness.contains("account_address::AccountAddress"));
+
+    let err = util::emit_test_harness(&program, &[script], script, &[]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected the source of 0 modules, but got 1"
+    );
+}
+
+#[test]
+fn resource_ops_counts_global_operations() {
+    let code = "
+        modules:
+        module M {
+            resource T { value: u64 }
+
+            public publish() {
+                move_to_sender<T>(T { value: 0 });
+                return;
+            }
+
+            public has(addr: address): bool {
+                return exists<T>(move(addr));
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let program = compiler.into_compiled_program().unwrap();
+    let summary = util::resource_ops(&program);
+
+    let module_id = program.modules[0].self_id();
+    let publish = summary[&(module_id.clone(), "publish".to_string())];
+    assert_eq!(publish.move_to_sender, 1);
+    assert_eq!(
+        publish.move_from + publish.borrow_global + publish.exists,
+        0
+    );
+    assert_eq!(summary[&(module_id, "has".to_string())].exists, 1);
+    let script_id = program.script.clone().into_module().self_id();
+    assert!(summary[&(script_id, "main".to_string())].is_empty());
+    assert_eq!(summary.len(), 3);
+}
+
+#[test]
+fn canonicalize_ignores_declaration_order() {
+    let compile = |functions: &str| {
+        let code = format!(
+            "
+            modules:
+            module M {{
+                struct S {{ value: u64 }}
+                {}
+            }}
+            script:
+            import 0x0.M;
+
+            main() {{
+                let flag: bool;
+                let x: u64;
+                flag = M.g();
+                x = M.f(move(flag));
+                return;
+            }}
+            ",
+            functions
+        );
+        let compiler = Compiler {
+            code: &code,
+            skip_stdlib_deps: true,
+            ..Compiler::default()
+        };
+        compiler.into_compiled_program().unwrap()
+    };
+    let f = "
+        public f(flag: bool): u64 {
+            let s: Self.S;
+            let value: u64;
+            s = S { value: 1 };
+            S { value: value } = move(s);
+            return move(value);
+        }";
+    let g = "
+        public g(): bool {
+            return true;
+        }";
+    let serialize = |program: &CompiledProgram| {
+        let mut blobs = vec![];
+        for module in &program.modules {
+            let mut blob = vec![];
+            module.serialize(&mut blob).unwrap();
+            blobs.push(blob);
+        }
+        let mut blob = vec![];
+        program.script.serialize(&mut blob).unwrap();
+        blobs.push(blob);
+        blobs
+    };
+
+    let first = compile(&format!("{}\n{}", f, g));
+    let second = compile(&format!("{}\n{}", g, f));
+    assert_ne!(serialize(&first), serialize(&second));
+
+    let first = util::canonicalize(first);
+    let second = util::canonicalize(second);
+    assert_eq!(serialize(&first), serialize(&second));
+    for module in first.modules {
+        VerifiedModule::new(module).unwrap();
+    }
+}
+
+#[test]
+fn callers_of_lists_direct_callers() {
+    let code = "
+        modules:
+        module M {
+            public f() {
+                Self.h();
+                return;
+            }
+
+            public g() {
+                Self.h();
+                Self.h();
+                return;
+            }
+
+            public h() {
+                return;
+            }
+        }
+        script:
+        import 0x0.M;
+
+        main() {
+            M.f();
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let program = compiler.into_compiled_program().unwrap();
+    let module_id = program.modules[0].self_id();
+    let function = |name: &str| (module_id.clone(), name.to_string());
+
+    assert_eq!(
+        util::callers_of(&program, &function("h")),
+        vec![function("f"), function("g")]
+    );
+    let script_id = program.script.clone().into_module().self_id();
+    assert_eq!(
+        util::callers_of(&program, &function("f")),
+        vec![(script_id, "main".to_string())]
+    );
+    assert!(util::callers_of(&program, &function("g")).is_empty());
+}
+
+#[test]
+fn storage_accesses_lists_global_reads_and_writes() {
+    let code = "
+        modules:
+        module M {
+            resource T { value: u64 }
+
+            public publish() {
+                move_to_sender<T>(T { value: 0 });
+                return;
+            }
+
+            public bump(addr: address) acquires T {
+                let t_ref: &mut Self.T;
+                let value_ref: &mut u64;
+                if (exists<T>(copy(addr))) {
+                    t_ref = borrow_global<T>(move(addr));
+                    value_ref = &mut move(t_ref).value;
+                    *move(value_ref) = 1;
+                }
+                return;
+            }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let compiler = Compiler {
+        code,
+        skip_stdlib_deps: true,
+        ..Compiler::default()
+    };
+    let program = compiler.into_compiled_program().unwrap();
+    let accesses = util::storage_accesses(&program);
+
+    let module_id = program.modules[0].self_id();
+    let tag = StructTag {
+        address: *module_id.address(),
+        module: "M".to_string(),
+        name: "T".to_string(),
+        type_params: vec![],
+    };
+    let bump = &accesses.functions[&(module_id.clone(), "bump".to_string())];
+    assert_eq!(
+        bump.reads.iter().collect::<Vec<_>>(),
+        vec![&(AddressKind::Dynamic, tag.clone())]
+    );
+    assert_eq!(
+        bump.writes.iter().collect::<Vec<_>>(),
+        vec![&(AddressKind::Dynamic, tag.clone())]
+    );
+    let publish = &accesses.functions[&(module_id, "publish".to_string())];
+    assert!(publish.reads.is_empty());
+    assert_eq!(
+        publish.writes.iter().collect::<Vec<_>>(),
+        vec![&(AddressKind::Sender, tag)]
+    );
+    assert_eq!(accesses.union().writes.len(), 2);
+}
@@ -0,0 +1,28 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::watch::WatchCompiler;
+use types::account_address::AccountAddress;
+
+#[test]
+fn only_changed_sources_recompile() {
+    let mut watcher = WatchCompiler::new(AccountAddress::default(), vec![]);
+    assert!(watcher.update_source("a", "main() { return; }").is_empty());
+    assert!(watcher
+        .update_source("b", "main() { let x: u64; x = 1; return; }")
+        .is_empty());
+    assert_eq!(watcher.generation("a"), Some(1));
+    assert_eq!(watcher.generation("b"), Some(1));
+
+    // An identical source is not recompiled.
+    assert!(watcher.update_source("a", "main() { return; }").is_empty());
+    assert_eq!(watcher.generation("a"), Some(1));
+
+    // A broken edit recompiles `a` only and reports the error.
+    let diagnostics = watcher.update_source("a", "main() { return }");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(watcher.compiled("a").is_none());
+    assert_eq!(watcher.generation("a"), Some(2));
+    assert_eq!(watcher.generation("b"), Some(1));
+    assert!(watcher.compiled("b").is_some());
+}
@@ -1,10 +1,37 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::Compiler;
+use bytecode_verifier::VerifiedModule;
+use canonical_serialization::SimpleDeserializer;
+use failure::prelude::*;
 use ir_to_bytecode::{compiler::compile_module, parser::parse_module};
-use std::{fs, path::Path};
-use types::account_address::AccountAddress;
-use vm::{access::ModuleAccess, file_format::CompiledModule};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    fs,
+    io::Read,
+    mem,
+    path::Path,
+};
+use types::{
+    account_address::AccountAddress,
+    byte_array::ByteArray,
+    language_storage::{ModuleId, StructTag},
+    transaction::{Program, TransactionArgument},
+};
+use vm::{
+    access::ModuleAccess,
+    file_format::{
+        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CompiledModule, CompiledModuleMut,
+        CompiledProgram, CompiledScriptMut, FunctionDefinitionIndex, LocalsSignature,
+        SignatureToken, StringPoolIndex, StructDefinitionIndex, StructFieldInformation, TableIndex,
+        TypeSignature, SELF_MODULE_NAME,
+    },
+    file_format_common::{BinaryConstants, TableType},
+};
 
 pub fn do_compile_module<T: ModuleAccess>(
     source_path: &Path,
@@ -16,3 +43,1144 @@ pub fn do_compile_module<T: ModuleAccess>(
     let parsed_module = parse_module(&source).unwrap();
     compile_module(address, &parsed_module, dependencies).unwrap()
 }
+
+/// Move IR code read from a file, which compiles with the file's path in its error messages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SourceFile {
+    /// The path of the file, as it is displayed in error messages.
+    pub name: String,
+    /// The code in the file.
+    pub code: String,
+}
+
+impl SourceFile {
+    /// Reads the code in the file at `path`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let code = fs::read_to_string(path)
+            .map_err(|err| format_err!("unable to read {}: {}", path.display(), err))?;
+        Ok(Self {
+            name: path.display().to_string(),
+            code,
+        })
+    }
+
+    /// Returns a compiler for the code that sends from `address`, with the other options left at
+    /// their defaults.
+    pub fn compiler(&self, address: AccountAddress) -> Compiler<'_> {
+        Compiler {
+            source_name: Some(&self.name),
+            ..Compiler::new(address, &self.code)
+        }
+    }
+}
+
+/// Reads the modules written by `Compiler::serialize_modules_to` until the end of `reader`.
+pub fn read_module_stream(reader: &mut impl Read) -> Result<Vec<CompiledModule>> {
+    let mut modules = vec![];
+    loop {
+        let mut len = [0u8; 4];
+        // The stream may only end between two modules.
+        if reader.read(&mut len[..1])? == 0 {
+            return Ok(modules);
+        }
+        reader.read_exact(&mut len[1..])?;
+        let mut blob = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut blob)?;
+        let module = CompiledModule::deserialize(&blob).map_err(|err| {
+            format_err!("module {} can't be deserialized: {}", modules.len(), err)
+        })?;
+        modules.push(module);
+    }
+}
+
+/// Splits the frames written by `Compiler::into_framed_programs` back into programs.
+pub fn read_framed_programs(mut frames: &[u8]) -> Result<Vec<Program>> {
+    let mut programs = vec![];
+    while !frames.is_empty() {
+        ensure!(
+            frames.len() >= 4,
+            "frame {} has a truncated length prefix",
+            programs.len()
+        );
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&frames[..4]);
+        let len = u32::from_le_bytes(len) as usize;
+        ensure!(
+            frames.len() - 4 >= len,
+            "frame {} is truncated: expected {} bytes, found {}",
+            programs.len(),
+            len,
+            frames.len() - 4
+        );
+        let program = SimpleDeserializer::deserialize(&frames[4..4 + len])
+            .map_err(|err| format_err!("frame {} isn't a program: {}", programs.len(), err))?;
+        programs.push(program);
+        frames = &frames[4 + len..];
+    }
+    Ok(programs)
+}
+
+/// The symbols of every module in a `CompiledProgram`, along with the ones of its script.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SymbolTable {
+    /// The symbols of the modules, in the order they are declared in the program.
+    pub modules: Vec<ModuleSymbols>,
+    /// The symbols of the script. The script is identified as a module named `<SELF>` and its
+    /// only function is `main`.
+    pub script: ModuleSymbols,
+}
+
+/// The symbols defined by a single module, each paired with its index in the respective table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModuleSymbols {
+    /// The id of the module.
+    pub id: ModuleId,
+    /// Function definitions, indexed into `function_defs`.
+    pub functions: Vec<(String, FunctionDefinitionIndex)>,
+    /// Struct definitions, indexed into `struct_defs`.
+    pub structs: Vec<(String, StructDefinitionIndex)>,
+    /// String constants, indexed into the string pool.
+    pub strings: Vec<(String, StringPoolIndex)>,
+    /// Byte array constants, indexed into the byte array pool.
+    pub byte_arrays: Vec<(ByteArray, ByteArrayPoolIndex)>,
+    /// Address constants, indexed into the address pool.
+    pub addresses: Vec<(AccountAddress, AddressPoolIndex)>,
+}
+
+impl ModuleSymbols {
+    /// Collects the symbols defined in `module`.
+    pub fn new(module: &impl ModuleAccess) -> Self {
+        let functions = module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .map(|(idx, def)| {
+                let handle = module.function_handle_at(def.function);
+                (
+                    module.string_at(handle.name).to_string(),
+                    FunctionDefinitionIndex::new(idx as TableIndex),
+                )
+            })
+            .collect();
+        let structs = module
+            .struct_defs()
+            .iter()
+            .enumerate()
+            .map(|(idx, def)| {
+                let handle = module.struct_handle_at(def.struct_handle);
+                (
+                    module.string_at(handle.name).to_string(),
+                    StructDefinitionIndex::new(idx as TableIndex),
+                )
+            })
+            .collect();
+        let strings = module
+            .string_pool()
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| (s.clone(), StringPoolIndex::new(idx as TableIndex)))
+            .collect();
+        let byte_arrays = module
+            .byte_array_pool()
+            .iter()
+            .enumerate()
+            .map(|(idx, b)| (b.clone(), ByteArrayPoolIndex::new(idx as TableIndex)))
+            .collect();
+        let addresses = module
+            .address_pool()
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| (*a, AddressPoolIndex::new(idx as TableIndex)))
+            .collect();
+        ModuleSymbols {
+            id: module.self_id(),
+            functions,
+            structs,
+            strings,
+            byte_arrays,
+            addresses,
+        }
+    }
+
+    /// Returns the definition index of the function named `name`, if any.
+    pub fn function(&self, name: &str) -> Option<FunctionDefinitionIndex> {
+        self.functions
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, idx)| *idx)
+    }
+
+    /// Returns the definition index of the struct named `name`, if any.
+    pub fn struct_def(&self, name: &str) -> Option<StructDefinitionIndex> {
+        self.structs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, idx)| *idx)
+    }
+}
+
+/// Builds the symbol table of a compiled program, covering its modules and its script.
+pub fn symbol_table(program: &CompiledProgram) -> SymbolTable {
+    SymbolTable {
+        modules: program.modules.iter().map(ModuleSymbols::new).collect(),
+        script: ModuleSymbols::new(&program.script.clone().into_module()),
+    }
+}
+
+/// Shrinks a failing program to a smaller source that still fails with the same error.
+///
+/// Returns `None` if `code` compiles. Otherwise whole brace-delimited blocks (modules, functions,
+/// loops, ...) and then single lines are greedily removed as long as compilation keeps failing
+/// with an identical error message. The result is meant for bug reports, not for compilation.
+pub fn minimize_failure(address: &AccountAddress, code: &str) -> Option<String> {
+    let compile_error = |lines: &[&str]| {
+        let code = lines.join("\n");
+        let compiler = Compiler {
+            address: *address,
+            code: &code,
+            ..Compiler::default()
+        };
+        compiler
+            .into_compiled_program()
+            .err()
+            .map(|err| err.to_string())
+    };
+
+    let mut lines: Vec<&str> = code.lines().collect();
+    let error = compile_error(&lines)?;
+    'shrink: loop {
+        for (start, end) in removal_candidates(&lines) {
+            let mut candidate = lines.clone();
+            candidate.drain(start..end);
+            if compile_error(&candidate).as_ref() == Some(&error) {
+                lines = candidate;
+                continue 'shrink;
+            }
+        }
+        break;
+    }
+    Some(lines.join("\n"))
+}
+
+/// Line ranges worth trying to remove, biggest first: every block that opens on a line and closes
+/// on a later one, followed by each single line.
+fn removal_candidates(lines: &[&str]) -> Vec<(usize, usize)> {
+    let brace_balance = |line: &str| {
+        line.chars().fold(0i64, |balance, c| match c {
+            '{' => balance + 1,
+            '}' => balance - 1,
+            _ => balance,
+        })
+    };
+
+    let mut blocks = vec![];
+    for (start, line) in lines.iter().enumerate() {
+        let mut balance = brace_balance(line);
+        if balance <= 0 {
+            continue;
+        }
+        for (end, line) in lines.iter().enumerate().skip(start + 1) {
+            balance += brace_balance(line);
+            if balance <= 0 {
+                blocks.push((start, end + 1));
+                break;
+            }
+        }
+    }
+    blocks.sort_by_key(|(start, end)| Reverse(end - start));
+    blocks.extend((0..lines.len()).map(|line| (line, line + 1)));
+    blocks
+}
+
+/// Renders the module dependency graph of `program` in Graphviz DOT format.
+///
+/// There is a node for every module of the program, every module in `deps` and the script, and an
+/// edge from each of them to every module it imports. Nodes are labeled `<address>.<name>` using
+/// the short form of the address; the script is labeled `script`.
+pub fn dependency_graph_dot(program: &CompiledProgram, deps: &[VerifiedModule]) -> String {
+    let node_name = |id: &ModuleId| {
+        if id.name() == SELF_MODULE_NAME {
+            "script".to_string()
+        } else {
+            format!("{}.{}", id.address().short_str(), id.name())
+        }
+    };
+
+    let script = program.script.clone().into_module();
+    let modules = deps
+        .iter()
+        .map(|dep| dep.as_inner())
+        .chain(&program.modules)
+        .chain(Some(&script));
+    let mut nodes = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+    for module in modules {
+        let self_name = node_name(&module.self_id());
+        for handle in module.module_handles().iter().skip(1) {
+            let dep_name = node_name(&module.module_id_for_handle(handle));
+            edges.insert((self_name.clone(), dep_name));
+        }
+        nodes.insert(self_name);
+    }
+
+    let mut dot = String::from("digraph dependencies {\n");
+    for node in &nodes {
+        writeln!(dot, "    \"{}\";", node).expect("writing to a string can't fail");
+    }
+    for (from, to) in &edges {
+        writeln!(dot, "    \"{}\" -> \"{}\";", from, to).expect("writing to a string can't fail");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The labels `annotated_hex_dump` prints above each table.
+const TABLE_LABELS: &[(TableType, &str)] = &[
+    (TableType::MODULE_HANDLES, "module handles table"),
+    (TableType::STRUCT_HANDLES, "struct handles table"),
+    (TableType::FUNCTION_HANDLES, "function handles table"),
+    (TableType::ADDRESS_POOL, "address pool table"),
+    (TableType::STRING_POOL, "string pool table"),
+    (TableType::BYTE_ARRAY_POOL, "byte array pool table"),
+    (TableType::MAIN, "main table"),
+    (TableType::STRUCT_DEFS, "struct definitions table"),
+    (TableType::FIELD_DEFS, "field definitions table"),
+    (TableType::FUNCTION_DEFS, "function definitions table"),
+    (TableType::TYPE_SIGNATURES, "type signatures table"),
+    (TableType::FUNCTION_SIGNATURES, "function signatures table"),
+    (TableType::LOCALS_SIGNATURES, "locals signatures table"),
+];
+
+/// Serializes `module` and renders the binary as a hex dump, 16 bytes per line.
+///
+/// Each section of the binary -- the header, the table directory and every table -- is preceded
+/// by a comment naming it, e.g. `// function handles table`. Lines start with the offset of their
+/// first byte.
+pub fn annotated_hex_dump(module: &CompiledModule) -> String {
+    let mut binary = vec![];
+    module
+        .serialize(&mut binary)
+        .expect("module must serialize");
+
+    let header_size = BinaryConstants::HEADER_SIZE;
+    let table_header_size = BinaryConstants::TABLE_HEADER_SIZE as usize;
+    let table_count = usize::from(binary[header_size - 1]);
+    let directory_end = header_size + table_count * table_header_size;
+    let mut sections = vec![(0, "header"), (header_size, "table directory")];
+    for table_header in binary[header_size..directory_end].chunks(table_header_size) {
+        let mut offset = [0u8; 4];
+        offset.copy_from_slice(&table_header[1..5]);
+        let label = TABLE_LABELS
+            .iter()
+            .find(|(kind, _)| *kind as u8 == table_header[0])
+            .map_or("unknown table", |(_, label)| *label);
+        sections.push((u32::from_le_bytes(offset) as usize, label));
+    }
+    sections.sort_by_key(|(start, _)| *start);
+
+    let mut dump = String::new();
+    for (idx, (start, label)) in sections.iter().enumerate() {
+        let end = sections
+            .get(idx + 1)
+            .map_or(binary.len(), |(next, _)| *next);
+        if *start == end {
+            continue;
+        }
+        writeln!(dump, "// {}", label).expect("writing to a string can't fail");
+        for (line, bytes) in binary[*start..end].chunks(16).enumerate() {
+            let bytes: Vec<_> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            writeln!(dump, "{:08x}: {}", start + line * 16, bytes.join(" "))
+                .expect("writing to a string can't fail");
+        }
+    }
+    dump
+}
+
+/// What a package needs to know about a module to compile against it, without its source.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceFile {
+    /// The module this is the interface of.
+    pub module_id: ModuleId,
+    /// The serialized module with every function body replaced by an abort. Handles, struct
+    /// layouts and function signatures are kept as they are.
+    pub stub: Vec<u8>,
+}
+
+impl InterfaceFile {
+    /// Deserializes and verifies the stub so it can be used as a compilation dependency.
+    pub fn into_verified_module(self) -> Result<VerifiedModule> {
+        let module = CompiledModule::deserialize(&self.stub).map_err(|err| {
+            format_err!(
+                "interface of {:?} can't be deserialized: {}",
+                self.module_id,
+                err
+            )
+        })?;
+        ensure!(
+            module.self_id() == self.module_id,
+            "interface of {:?} holds module {:?}",
+            self.module_id,
+            module.self_id()
+        );
+        VerifiedModule::new(module).map_err(|(_, errors)| {
+            format_err!(
+                "interface of {:?} failed verification: {:?}",
+                self.module_id,
+                errors
+            )
+        })
+    }
+}
+
+/// Produces the interface of `module`, i.e. the module stripped of its code.
+///
+/// Calling a function of the stub aborts, so the stub can only be used to compile and verify
+/// against, never to publish or run.
+pub fn emit_interface(module: &CompiledModule) -> InterfaceFile {
+    let mut stub = module.clone().into_inner();
+    for function_def in &mut stub.function_defs {
+        function_def.acquires_global_resources.clear();
+        if !function_def.is_native() {
+            function_def.code.max_stack_size = 1;
+            function_def.code.code = vec![Bytecode::LdConst(0), Bytecode::Abort];
+        }
+    }
+    let stub = stub
+        .freeze()
+        .expect("stripping code can't break the bounds of a module");
+
+    let mut blob = vec![];
+    stub.serialize(&mut blob).expect("module must serialize");
+    InterfaceFile {
+        module_id: module.self_id(),
+        stub: blob,
+    }
+}
+
+/// The outcome of compiling one file of a `CompileReport`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: String,
+    pub success: bool,
+    /// The compile error if compilation failed, the lint warnings otherwise.
+    pub diagnostics: Vec<String>,
+}
+
+/// The outcome of compiling every file of a directory, see `compile_dir_report`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CompileReport {
+    /// One entry per file, ordered by path.
+    pub files: Vec<FileReport>,
+}
+
+impl CompileReport {
+    /// Returns whether every file compiled.
+    pub fn is_success(&self) -> bool {
+        self.files.iter().all(|file| file.success)
+    }
+}
+
+/// Compiles every `.mvir` file directly inside `dir` against the stdlib and reports the outcome
+/// of each, rather than stopping at the first failure.
+pub fn compile_dir_report(dir: &Path, address: &AccountAddress) -> Result<CompileReport> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "mvir")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut files = vec![];
+    for path in paths {
+        let code = fs::read_to_string(&path)?;
+        let compiler = Compiler {
+            address: *address,
+            code: &code,
+            ..Compiler::default()
+        };
+        let (success, diagnostics) = match compiler.into_lint_diagnostics() {
+            Ok(warnings) => (
+                true,
+                warnings
+                    .into_iter()
+                    .map(|warning| warning.message)
+                    .collect(),
+            ),
+            Err(err) => (false, vec![err.to_string()]),
+        };
+        files.push(FileReport {
+            path: path.display().to_string(),
+            success,
+            diagnostics,
+        });
+    }
+    Ok(CompileReport { files })
+}
+
+/// Generates the source of an `e2e_tests` test that runs `program` with `args`, as a starting
+/// point for a new test. `module_sources` and `script_source` are the code `program` was
+/// compiled from, one entry per module in the order of `program.modules`.
+///
+/// The test compiles the modules in order with `compile::compile_module_with_deps`, at the
+/// addresses they were compiled at and against the modules before them, and stores them directly
+/// in a `FakeExecutor` without publishing transactions. It then has a fresh account send the
+/// script, compiled with
+/// `compile::compile_script_with_deps` against the modules, or with
+/// `compile::compile_script_with_address` if there are none, and checks that it executes.
+pub fn emit_test_harness(
+    program: &CompiledProgram,
+    module_sources: &[&str],
+    script_source: &str,
+    args: &[TransactionArgument],
+) -> Result<String> {
+    ensure!(
+        module_sources.len() == program.modules.len(),
+        "expected the source of {} modules, but got {}",
+        program.modules.len(),
+        module_sources.len()
+    );
+    let byte_list = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|byte| format!("{:#04x}", byte))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let render_address = |address: &AccountAddress| {
+        format!("AccountAddress::new([{}])", byte_list(address.as_ref()))
+    };
+
+    let mut types_imports = vec!["transaction::{TransactionArgument, TransactionStatus}"];
+    let mut rendered_args = vec![];
+    for arg in args {
+        rendered_args.push(match arg {
+            TransactionArgument::U64(value) => format!("TransactionArgument::U64({})", value),
+            TransactionArgument::Address(address) => {
+                types_imports.push("account_address::AccountAddress");
+                format!("TransactionArgument::Address({})", render_address(address))
+            }
+            TransactionArgument::ByteArray(byte_array) => {
+                types_imports.push("byte_array::ByteArray");
+                format!(
+                    "TransactionArgument::ByteArray(ByteArray::new(vec![{}]))",
+                    byte_list(byte_array.as_bytes())
+                )
+            }
+            TransactionArgument::String(string) => {
+                format!("TransactionArgument::String({:?}.to_string())", string)
+            }
+        });
+    }
+    types_imports.push("vm_error::{ExecutionStatus, VMStatus}");
+
+    let mut constants = String::new();
+    let mut compile_modules = String::new();
+    let mut compile_imports = vec![];
+    let mut vm_imports = "";
+    let compile_script = if program.modules.is_empty() {
+        compile_imports.push("compile_script_with_address");
+        "compile_script_with_address(sender.address(), SCRIPT)"
+    } else {
+        types_imports.push("account_address::AccountAddress");
+        compile_imports.push("compile_module_with_deps");
+        compile_imports.push("compile_script_with_deps");
+        vm_imports = "use vm::{access::ModuleAccess, CompiledModule};\n";
+
+        let mut module_list = String::new();
+        for (module, source) in program.modules.iter().zip(module_sources) {
+            let id = module.self_id();
+            let constant = format!("MODULE_{}", id.name().to_uppercase());
+            constants.push_str(&format!("const {}: &str = {:?};\n", constant, source));
+            module_list.push_str(&format!(
+                "        ({}, {}),\n",
+                render_address(id.address()),
+                constant
+            ));
+        }
+        compile_modules = format!(
+            r#"    let mut modules = vec![];
+    for (address, code) in &[
+{module_list}    ] {{
+        let blob = compile_module_with_deps(address, code, modules.clone());
+        let module = CompiledModule::deserialize(&blob).expect("module must deserialize");
+        executor.add_module(&module.self_id(), &module);
+        modules.push(module);
+    }}
+
+"#,
+            module_list = module_list
+        );
+        "compile_script_with_deps(sender.address(), SCRIPT, modules)"
+    };
+    constants.push_str(&format!("const SCRIPT: &str = {:?};\n", script_source));
+    types_imports.sort();
+    types_imports.dedup();
+    let compile_imports = match compile_imports.as_slice() {
+        [single] => single.to_string(),
+        imports => format!("{{{}}}", imports.join(", ")),
+    };
+
+    Ok(format!(
+        r#"use crate::{{
+    account::AccountData,
+    compile::{compile_imports},
+    executor::FakeExecutor,
+}};
+use types::{{{types_imports}}};
+{vm_imports}
+{constants}
+#[test]
+fn run_script() {{
+    let mut executor = FakeExecutor::from_genesis_file();
+
+{compile_modules}    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+    let script = {compile_script};
+    let args: Vec<TransactionArgument> = vec![{args}];
+    let txn = sender
+        .account()
+        .create_signed_txn_with_args(script, args, 10, 100_000, 1);
+    let output = executor.execute_transaction(txn);
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Execution(ExecutionStatus::Executed))
+    );
+}}
+"#,
+        compile_imports = compile_imports,
+        types_imports = types_imports.join(", "),
+        vm_imports = vm_imports,
+        constants = constants,
+        compile_modules = compile_modules,
+        compile_script = compile_script,
+        args = rendered_args.join(", "),
+    ))
+}
+
+/// How often a function performs each kind of global resource operation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ResourceOps {
+    /// Number of `MoveToSender` instructions.
+    pub move_to_sender: usize,
+    /// Number of `MoveFrom` instructions.
+    pub move_from: usize,
+    /// Number of `BorrowGlobal` instructions.
+    pub borrow_global: usize,
+    /// Number of `Exists` instructions.
+    pub exists: usize,
+}
+
+impl ResourceOps {
+    /// Returns true if the function performs no global resource operation.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Counts the global resource operations of every function defined in `program`, keyed by the
+/// module and the name of the function.
+///
+/// Every function definition, including natives and the script's `main`, has an entry; the script
+/// is identified as the module named `<SELF>`. The counts are static: an instruction inside a
+/// loop is counted once.
+pub fn resource_ops(program: &CompiledProgram) -> BTreeMap<(ModuleId, String), ResourceOps> {
+    let script = program.script.clone().into_module();
+    let mut summary = BTreeMap::new();
+    for module in program.modules.iter().chain(Some(&script)) {
+        let module_id = module.self_id();
+        for def in module.function_defs() {
+            let mut ops = ResourceOps::default();
+            for bytecode in &def.code.code {
+                match bytecode {
+                    Bytecode::MoveToSender(_, _) => ops.move_to_sender += 1,
+                    Bytecode::MoveFrom(_, _) => ops.move_from += 1,
+                    Bytecode::BorrowGlobal(_, _) => ops.borrow_global += 1,
+                    Bytecode::Exists(_, _) => ops.exists += 1,
+                    _ => (),
+                }
+            }
+            let handle = module.function_handle_at(def.function);
+            let name = module.string_at(handle.name).to_string();
+            summary.insert((module_id.clone(), name), ops);
+        }
+    }
+    summary
+}
+
+/// Reorders the tables of every module and of the script of `program` into a canonical order,
+/// renumbering all indices into them.
+///
+/// Pools are sorted by value and every other table by its (renumbered) contents, so programs that
+/// only differ in the order of their declarations canonicalize to the same bytes. The self module
+/// handle stays first and the fields of each struct stay contiguous, so the result is as valid as
+/// `program`.
+pub fn canonicalize(program: CompiledProgram) -> CompiledProgram {
+    let modules = program
+        .modules
+        .into_iter()
+        .map(|module| {
+            let mut module = module.into_inner();
+            canonicalize_module(&mut module);
+            module
+                .freeze()
+                .expect("canonicalization must preserve bounds")
+        })
+        .collect();
+
+    let mut script = program.script.into_inner().into_module();
+    canonicalize_module(&mut script);
+    let main = script
+        .function_defs
+        .pop()
+        .expect("script must have a main function");
+    let script = CompiledScriptMut {
+        module_handles: script.module_handles,
+        struct_handles: script.struct_handles,
+        function_handles: script.function_handles,
+        type_signatures: script.type_signatures,
+        function_signatures: script.function_signatures,
+        locals_signatures: script.locals_signatures,
+        string_pool: script.string_pool,
+        byte_array_pool: script.byte_array_pool,
+        address_pool: script.address_pool,
+        main,
+    }
+    .freeze()
+    .expect("canonicalization must preserve bounds");
+
+    CompiledProgram { modules, script }
+}
+
+fn canonicalize_module(module: &mut CompiledModuleMut) {
+    // Tables are sorted in dependency order: an entry is only sorted once all indices it holds
+    // have been renumbered, so its sort key is canonical too.
+    let strings = sort_table(&mut module.string_pool, 0, |string| string.clone());
+    let byte_arrays = sort_table(&mut module.byte_array_pool, 0, |bytes| bytes.clone());
+    let addresses = sort_table(&mut module.address_pool, 0, |address| *address);
+
+    for handle in &mut module.module_handles {
+        renumber(&addresses, &mut handle.address.0);
+        renumber(&strings, &mut handle.name.0);
+    }
+    let module_handles = sort_table(&mut module.module_handles, 1, |handle| handle.clone());
+
+    for handle in &mut module.struct_handles {
+        renumber(&module_handles, &mut handle.module.0);
+        renumber(&strings, &mut handle.name.0);
+    }
+    let struct_handles = sort_table(&mut module.struct_handles, 0, |handle| handle.clone());
+
+    for TypeSignature(token) in &mut module.type_signatures {
+        renumber_token(&struct_handles, token);
+    }
+    let type_signatures = sort_table(&mut module.type_signatures, 0, |sig| sig.0.clone());
+
+    for sig in &mut module.function_signatures {
+        for token in sig.return_types.iter_mut().chain(&mut sig.arg_types) {
+            renumber_token(&struct_handles, token);
+        }
+    }
+    let function_signatures = sort_table(&mut module.function_signatures, 0, |sig| {
+        (
+            sig.return_types.clone(),
+            sig.arg_types.clone(),
+            sig.type_formals.clone(),
+        )
+    });
+
+    for LocalsSignature(tokens) in &mut module.locals_signatures {
+        for token in tokens {
+            renumber_token(&struct_handles, token);
+        }
+    }
+    let locals_signatures = sort_table(&mut module.locals_signatures, 0, |sig| sig.0.clone());
+
+    for handle in &mut module.function_handles {
+        renumber(&module_handles, &mut handle.module.0);
+        renumber(&strings, &mut handle.name.0);
+        renumber(&function_signatures, &mut handle.signature.0);
+    }
+    let function_handles = sort_table(&mut module.function_handles, 0, |handle| {
+        (handle.module, handle.name, handle.signature)
+    });
+
+    for def in &mut module.struct_defs {
+        renumber(&struct_handles, &mut def.struct_handle.0);
+    }
+    let struct_defs = sort_table(&mut module.struct_defs, 0, |def| def.struct_handle);
+
+    // Fields are laid out in the order of their (sorted) structs. Fields no struct owns can't be
+    // referenced, they are kept at the end.
+    let old_field_defs = mem::replace(&mut module.field_defs, vec![]);
+    let mut new_field_indices: Vec<Option<TableIndex>> = vec![None; old_field_defs.len()];
+    for def in &mut module.struct_defs {
+        if let StructFieldInformation::Declared {
+            field_count,
+            fields,
+        } = &mut def.field_information
+        {
+            let start = fields.0 as usize;
+            fields.0 = module.field_defs.len() as TableIndex;
+            for old_idx in start..start + *field_count as usize {
+                new_field_indices[old_idx] = Some(module.field_defs.len() as TableIndex);
+                module.field_defs.push(old_field_defs[old_idx].clone());
+            }
+        }
+    }
+    for (old_idx, new_idx) in new_field_indices.iter_mut().enumerate() {
+        if new_idx.is_none() {
+            *new_idx = Some(module.field_defs.len() as TableIndex);
+            module.field_defs.push(old_field_defs[old_idx].clone());
+        }
+    }
+    let field_defs: Vec<TableIndex> = new_field_indices
+        .into_iter()
+        .map(|new_idx| new_idx.expect("every field has been placed"))
+        .collect();
+    for def in &mut module.field_defs {
+        renumber(&struct_handles, &mut def.struct_.0);
+        renumber(&strings, &mut def.name.0);
+        renumber(&type_signatures, &mut def.signature.0);
+    }
+
+    for def in &mut module.function_defs {
+        renumber(&function_handles, &mut def.function.0);
+        for acquired in &mut def.acquires_global_resources {
+            renumber(&struct_defs, &mut acquired.0);
+        }
+        def.acquires_global_resources.sort();
+        renumber(&locals_signatures, &mut def.code.locals.0);
+        for bytecode in &mut def.code.code {
+            match bytecode {
+                Bytecode::LdStr(idx) => renumber(&strings, &mut idx.0),
+                Bytecode::LdByteArray(idx) => renumber(&byte_arrays, &mut idx.0),
+                Bytecode::LdAddr(idx) => renumber(&addresses, &mut idx.0),
+                Bytecode::Call(idx, type_actuals) => {
+                    renumber(&function_handles, &mut idx.0);
+                    renumber(&locals_signatures, &mut type_actuals.0);
+                }
+                Bytecode::Pack(idx, type_actuals)
+                | Bytecode::Unpack(idx, type_actuals)
+                | Bytecode::BorrowGlobal(idx, type_actuals)
+                | Bytecode::Exists(idx, type_actuals)
+                | Bytecode::MoveFrom(idx, type_actuals)
+                | Bytecode::MoveToSender(idx, type_actuals) => {
+                    renumber(&struct_defs, &mut idx.0);
+                    renumber(&locals_signatures, &mut type_actuals.0);
+                }
+                Bytecode::MutBorrowField(idx) | Bytecode::ImmBorrowField(idx) => {
+                    renumber(&field_defs, &mut idx.0)
+                }
+                _ => (),
+            }
+        }
+    }
+    sort_table(&mut module.function_defs, 0, |def| def.function);
+}
+
+/// Sorts `table` by `key`, keeping the first `pinned` entries in place, and returns the new index
+/// of every entry, indexed by its old one.
+fn sort_table<T, K: Ord>(
+    table: &mut Vec<T>,
+    pinned: usize,
+    key: impl Fn(&T) -> K,
+) -> Vec<TableIndex> {
+    let mut entries: Vec<_> = table.drain(..).enumerate().collect();
+    let pinned = pinned.min(entries.len());
+    entries[pinned..].sort_by_key(|(_, entry)| key(entry));
+    let mut new_indices = vec![0; entries.len()];
+    for (new_idx, (old_idx, entry)) in entries.into_iter().enumerate() {
+        new_indices[old_idx] = new_idx as TableIndex;
+        table.push(entry);
+    }
+    new_indices
+}
+
+fn renumber(new_indices: &[TableIndex], idx: &mut TableIndex) {
+    *idx = new_indices[*idx as usize];
+}
+
+fn renumber_token(struct_handles: &[TableIndex], token: &mut SignatureToken) {
+    match token {
+        SignatureToken::Struct(idx, type_actuals) => {
+            renumber(struct_handles, &mut idx.0);
+            for type_actual in type_actuals {
+                renumber_token(struct_handles, type_actual);
+            }
+        }
+        SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+            renumber_token(struct_handles, inner)
+        }
+        SignatureToken::Bool
+        | SignatureToken::U64
+        | SignatureToken::String
+        | SignatureToken::ByteArray
+        | SignatureToken::Address
+        | SignatureToken::TypeParameter(_) => (),
+    }
+}
+
+/// Builds the call graph of `program`: for every function defined by its modules or script, the
+/// functions it calls directly, keyed by module and name. Callees may be defined outside of
+/// `program`. The script is identified as the module named `<SELF>`.
+pub fn call_graph(
+    program: &CompiledProgram,
+) -> BTreeMap<(ModuleId, String), BTreeSet<(ModuleId, String)>> {
+    let script = program.script.clone().into_module();
+    let mut graph = BTreeMap::new();
+    for module in program.modules.iter().chain(Some(&script)) {
+        let function_id = |idx| {
+            let handle = module.function_handle_at(idx);
+            let module_handle = module.module_handle_at(handle.module);
+            (
+                module.module_id_for_handle(module_handle),
+                module.string_at(handle.name).to_string(),
+            )
+        };
+        for def in module.function_defs() {
+            let callees = def
+                .code
+                .code
+                .iter()
+                .filter_map(|bytecode| match bytecode {
+                    Bytecode::Call(idx, _) => Some(function_id(*idx)),
+                    _ => None,
+                })
+                .collect();
+            graph.insert(function_id(def.function), callees);
+        }
+    }
+    graph
+}
+
+/// Returns every function of `program` that calls `target` directly (see `call_graph`), in
+/// order.
+pub fn callers_of(
+    program: &CompiledProgram,
+    target: &(ModuleId, String),
+) -> Vec<(ModuleId, String)> {
+    call_graph(program)
+        .into_iter()
+        .filter(|(_, callees)| callees.contains(target))
+        .map(|(caller, _)| caller)
+        .collect()
+}
+
+/// The account a global storage access is made to.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum AddressKind {
+    /// The account of the transaction sender, as with `MoveToSender`.
+    Sender,
+    /// An account whose address is only known at runtime, as with `BorrowGlobal`, `Exists` and
+    /// `MoveFrom`.
+    Dynamic,
+}
+
+/// The global resources a function reads and writes directly.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FunctionAccesses {
+    /// Resources checked with `Exists`.
+    pub reads: BTreeSet<(AddressKind, StructTag)>,
+    /// Resources published with `MoveToSender`, removed with `MoveFrom` or borrowed with
+    /// `BorrowGlobal`. The borrow is mutable, so it counts as a write.
+    pub writes: BTreeSet<(AddressKind, StructTag)>,
+}
+
+/// The global storage accesses of every function of a program, see `storage_accesses`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StorageAccessSet {
+    /// The accesses of each function, keyed by module and name. The script is identified as the
+    /// module named `<SELF>`.
+    pub functions: BTreeMap<(ModuleId, String), FunctionAccesses>,
+}
+
+impl StorageAccessSet {
+    /// Returns the union of the accesses of all functions.
+    pub fn union(&self) -> FunctionAccesses {
+        let mut union = FunctionAccesses::default();
+        for accesses in self.functions.values() {
+            union.reads.extend(accesses.reads.iter().cloned());
+            union.writes.extend(accesses.writes.iter().cloned());
+        }
+        union
+    }
+}
+
+/// Collects the global resources each function of `program` reads and writes, from its global
+/// storage instructions.
+///
+/// Only direct accesses are listed: the accesses of a callee are not added to its callers, and
+/// callees outside of `program` aren't looked at. Resources are identified by their `StructTag`
+/// without type parameters, as the runtime keys them.
+pub fn storage_accesses(program: &CompiledProgram) -> StorageAccessSet {
+    let script = program.script.clone().into_module();
+    let mut functions = BTreeMap::new();
+    for module in program.modules.iter().chain(Some(&script)) {
+        let module_id = module.self_id();
+        for def in module.function_defs() {
+            let mut accesses = FunctionAccesses::default();
+            for bytecode in &def.code.code {
+                match bytecode {
+                    Bytecode::Exists(idx, _) => {
+                        accesses
+                            .reads
+                            .insert((AddressKind::Dynamic, struct_tag(module, *idx)));
+                    }
+                    Bytecode::BorrowGlobal(idx, _) | Bytecode::MoveFrom(idx, _) => {
+                        accesses
+                            .writes
+                            .insert((AddressKind::Dynamic, struct_tag(module, *idx)));
+                    }
+                    Bytecode::MoveToSender(idx, _) => {
+                        accesses
+                            .writes
+                            .insert((AddressKind::Sender, struct_tag(module, *idx)));
+                    }
+                    _ => (),
+                }
+            }
+            let handle = module.function_handle_at(def.function);
+            let name = module.string_at(handle.name).to_string();
+            functions.insert((module_id.clone(), name), accesses);
+        }
+    }
+    StorageAccessSet { functions }
+}
+
+fn struct_tag(module: &CompiledModule, idx: StructDefinitionIndex) -> StructTag {
+    let struct_handle = module.struct_handle_at(module.struct_def_at(idx).struct_handle);
+    let module_handle = module.module_handle_at(struct_handle.module);
+    StructTag {
+        address: *module.address_at(module_handle.address),
+        module: module.string_at(module_handle.name).to_string(),
+        name: module.string_at(struct_handle.name).to_string(),
+        type_params: vec![],
+    }
+}
+
+/// A human-readable view of a `CompiledProgram`, see `Compiler::into_json`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProgramDump {
+    pub script: ModuleDump,
+    pub modules: Vec<ModuleDump>,
+}
+
+impl ProgramDump {
+    pub fn new(program: &CompiledProgram) -> Self {
+        ProgramDump {
+            script: ModuleDump::new(&program.script.clone().into_module()),
+            modules: program.modules.iter().map(ModuleDump::new).collect(),
+        }
+    }
+}
+
+/// A human-readable view of the tables of a compiled module, or of a script as the module with
+/// its `main`. Indices into the tables are resolved to the names and values they refer to, and
+/// signatures and bytecode are rendered the way `Debug` prints them.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ModuleDump {
+    /// The modules as `0x<address>.<name>`, the module itself first.
+    pub module_handles: Vec<String>,
+    /// The structs as `<module>.<name>`.
+    pub struct_handles: Vec<String>,
+    pub function_handles: Vec<FunctionHandleDump>,
+    pub function_signatures: Vec<String>,
+    pub string_pool: Vec<String>,
+    pub byte_array_pool: Vec<String>,
+    pub address_pool: Vec<String>,
+    pub function_defs: Vec<FunctionDefinitionDump>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionHandleDump {
+    pub module: String,
+    pub name: String,
+    pub signature: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDefinitionDump {
+    pub name: String,
+    pub flags: u8,
+    /// The bytecode, one instruction per entry. Native functions have none.
+    pub code: Vec<String>,
+}
+
+impl ModuleDump {
+    pub fn new(module: &CompiledModule) -> Self {
+        let module_name = |idx| {
+            let handle = module.module_handle_at(idx);
+            module.string_at(handle.name).to_string()
+        };
+        ModuleDump {
+            module_handles: module
+                .module_handles()
+                .iter()
+                .map(|handle| {
+                    format!(
+                        "0x{}.{}",
+                        module.address_at(handle.address),
+                        module.string_at(handle.name)
+                    )
+                })
+                .collect(),
+            struct_handles: module
+                .struct_handles()
+                .iter()
+                .map(|handle| {
+                    format!(
+                        "{}.{}",
+                        module_name(handle.module),
+                        module.string_at(handle.name)
+                    )
+                })
+                .collect(),
+            function_handles: module
+                .function_handles()
+                .iter()
+                .map(|handle| FunctionHandleDump {
+                    module: module_name(handle.module),
+                    name: module.string_at(handle.name).to_string(),
+                    signature: format!("{:?}", module.function_signature_at(handle.signature)),
+                })
+                .collect(),
+            function_signatures: module
+                .function_signatures()
+                .iter()
+                .map(|signature| format!("{:?}", signature))
+                .collect(),
+            string_pool: module.string_pool().to_vec(),
+            byte_array_pool: module
+                .byte_array_pool()
+                .iter()
+                .map(|bytes| format!("{:?}", bytes))
+                .collect(),
+            address_pool: module
+                .address_pool()
+                .iter()
+                .map(|address| format!("0x{}", address))
+                .collect(),
+            function_defs: module
+                .function_defs()
+                .iter()
+                .map(|def| FunctionDefinitionDump {
+                    name: module
+                        .string_at(module.function_handle_at(def.function).name)
+                        .to_string(),
+                    flags: def.flags,
+                    code: def
+                        .code
+                        .code
+                        .iter()
+                        .map(|bytecode| format!("{:?}", bytecode))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
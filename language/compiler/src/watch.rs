@@ -0,0 +1,88 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A long-lived compiler for editor integrations.
+
+use bytecode_verifier::VerifiedModule;
+use ir_to_bytecode::{compiler::compile_program, parser::parse_program};
+use std::collections::BTreeMap;
+use types::account_address::AccountAddress;
+use vm::file_format::CompiledProgram;
+
+/// The latest state of a single watched source.
+#[derive(Clone, Debug)]
+struct WatchedSource {
+    code: String,
+    generation: u64,
+    compiled: Option<CompiledProgram>,
+    diagnostics: Vec<String>,
+}
+
+/// Holds a set of named Move IR programs and recompiles them as they change.
+///
+/// The dependency set is fixed at construction and shared by every compilation, so only the
+/// program whose source actually changed is recompiled.
+#[derive(Clone, Debug)]
+pub struct WatchCompiler {
+    address: AccountAddress,
+    deps: Vec<VerifiedModule>,
+    sources: BTreeMap<String, WatchedSource>,
+}
+
+impl WatchCompiler {
+    /// Creates a watcher compiling with `address` as the sender against `deps`.
+    pub fn new(address: AccountAddress, deps: Vec<VerifiedModule>) -> Self {
+        WatchCompiler {
+            address,
+            deps,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the source of the program `name` and returns its diagnostics. An empty list means the
+    /// program compiled.
+    ///
+    /// Programs are only recompiled if `code` differs from the last source seen for `name`.
+    pub fn update_source(&mut self, name: &str, code: &str) -> Vec<String> {
+        if let Some(source) = self.sources.get(name) {
+            if source.code == code {
+                return source.diagnostics.clone();
+            }
+        }
+
+        let result = parse_program(code)
+            .and_then(|program| compile_program(&self.address, &program, &self.deps));
+        let (compiled, diagnostics) = match result {
+            Ok(compiled) => (Some(compiled), vec![]),
+            Err(err) => (None, vec![err.to_string()]),
+        };
+        let generation = self.generation(name).map_or(1, |generation| generation + 1);
+        self.sources.insert(
+            name.to_string(),
+            WatchedSource {
+                code: code.to_string(),
+                generation,
+                compiled,
+                diagnostics: diagnostics.clone(),
+            },
+        );
+        diagnostics
+    }
+
+    /// Stops watching the program `name`.
+    pub fn remove_source(&mut self, name: &str) {
+        self.sources.remove(name);
+    }
+
+    /// Returns the latest successful compilation of `name`, if its current source compiles.
+    pub fn compiled(&self, name: &str) -> Option<&CompiledProgram> {
+        self.sources
+            .get(name)
+            .and_then(|source| source.compiled.as_ref())
+    }
+
+    /// Returns how many times `name` has been compiled, or `None` if it isn't watched.
+    pub fn generation(&self, name: &str) -> Option<u64> {
+        self.sources.get(name).map(|source| source.generation)
+    }
+}
@@ -3,33 +3,65 @@
 
 //! Support for compiling scripts and modules in tests.
 
+use bytecode_verifier::VerifiedModule;
 use compiler::Compiler;
+use failure::prelude::*;
+use std::cell::Cell;
 use types::{
     account_address::AccountAddress,
     transaction::{Program, TransactionArgument},
 };
-use bytecode_verifier::VerifiedModule;
-use vm::CompiledModule;
+use vm::{file_format::CompiledScript, CompiledModule};
+
+thread_local! {
+    static DEFAULT_SENDER: Cell<AccountAddress> = Cell::new(AccountAddress::default());
+}
+
+/// Set the address that the helpers without an `address` parameter, like `compile_script` and
+/// `compile_module`, compile with on the current thread. It starts out as `0x0`.
+///
+/// Each test runs on a thread of its own, so setting this doesn't affect other tests.
+pub fn set_default_sender(address: AccountAddress) {
+    DEFAULT_SENDER.with(|sender| sender.set(address));
+}
+
+/// The address that the helpers without an `address` parameter compile with on the current
+/// thread, see `set_default_sender`.
+pub fn default_sender() -> AccountAddress {
+    DEFAULT_SENDER.with(Cell::get)
+}
 
 /// Compile the provided Move code into a blob which can be used as the code for a [`Program`] or
 /// a [`Script`].
 ///
-/// The script is compiled with the default account address (`0x0`).
+/// The script is compiled with the `default_sender` address.
 pub fn compile_script(code: &str) -> Vec<u8> {
-    let compiler = Compiler {
-        code,
-        ..Compiler::default()
-    };
-    compiler.into_script_blob().unwrap()
+    try_compile_script(code).unwrap()
+}
+
+/// Like `compile_script`, but returns the parser or compiler error instead of panicking.
+pub fn try_compile_script(code: &str) -> Result<Vec<u8>> {
+    let compiler = Compiler::new(default_sender(), code);
+    compiler.into_script_blob()
 }
 
 /// Compile the provided Move code into a blob which can be used as a [`Script`].
 pub fn compile_script_with_address(address: &AccountAddress, code: &str) -> Vec<u8> {
-    let compiler = Compiler {
-        address: *address,
-        code,
-        ..Compiler::default()
-    };
+    let compiler = Compiler::new(*address, code);
+    compiler.into_script_blob().unwrap()
+}
+
+/// Compile the provided Move code into a blob which can be used as a [`Script`], linking against
+/// `deps` in addition to the stdlib.
+///
+/// Every dependency has to pass the bytecode verifier.
+pub fn compile_script_with_deps(
+    address: &AccountAddress,
+    code: &str,
+    deps: Vec<CompiledModule>,
+) -> Vec<u8> {
+    let mut compiler = Compiler::new(*address, code);
+    compiler.extra_deps = verify_deps(deps);
     compiler.into_script_blob().unwrap()
 }
 
@@ -40,77 +72,131 @@ pub fn compile_program_with_address(
     code: &str,
     args: Vec<TransactionArgument>,
 ) -> Program {
-    let compiler = Compiler {
-        address: *address,
-        code,
-        ..Compiler::default()
-    };
+    let compiler = Compiler::new(*address, code);
     compiler.into_program(args).unwrap()
 }
 
-pub fn compile_program_with_address_return_deps (
+pub fn compile_program_with_address_return_deps(
     address: &AccountAddress,
     code: &str,
     args: Vec<TransactionArgument>,
-) -> (Program,Vec<VerifiedModule>,Vec<CompiledModule>) {
-    let compiler = Compiler {
-        address: *address,
-        code,
-        ..Compiler::default()
-    };
+) -> (Program, Vec<VerifiedModule>, Vec<CompiledModule>) {
+    let compiler = Compiler::new(*address, code);
     compiler.into_program_and_deps(args).unwrap()
 }
 
+/// Compile the provided Move code and arguments into a `Program` using `address` as the
+/// self address for any modules in `code`, linking against `deps` in addition to the stdlib.
+///
+/// Every dependency has to pass the bytecode verifier.
 pub fn compile_program_with_address_with_deps(
     address: &AccountAddress,
     code: &str,
     args: Vec<TransactionArgument>,
-    mut deps:Vec<CompiledModule>
+    deps: Vec<CompiledModule>,
 ) -> Program {
-    let depsv = VerifiedModule::constract(deps[0].clone());
-    let mut compiler = Compiler {
-        address: *address,
-        skip_stdlib_deps:false,
-        code,
-        extra_deps:vec![depsv],
-        ..Compiler::default()
-    };
-    //compiler.add_deps(deps);
-    compiler.into_program_2(args,deps).unwrap()
+    let mut compiler = Compiler::new(*address, code);
+    compiler.extra_deps = verify_deps(deps);
+    compiler.into_program(args).unwrap()
 }
 
+fn verify_deps(deps: Vec<CompiledModule>) -> Vec<VerifiedModule> {
+    deps.into_iter()
+        .map(|dep| VerifiedModule::new(dep).expect("dependency must verify"))
+        .collect()
+}
 
 /// Compile the provided Move code and arguments into a `Program`.
 ///
 /// This supports both scripts and modules defined in the same Move code. The code is compiled with
-/// the default account address (`0x0`).
+/// the `default_sender` address.
 pub fn compile_program(code: &str, args: Vec<TransactionArgument>) -> Program {
-    let compiler = Compiler {
-        code,
-        ..Compiler::default()
-    };
-    compiler.into_program(args).unwrap()
+    try_compile_program(code, args).unwrap()
+}
+
+/// Like `compile_program`, but also returns the dependencies the program was linked against and
+/// the modules defined in `code`.
+///
+/// This is the `default_sender` counterpart of `compile_program_with_address_return_deps`.
+pub fn compile_program_return_deps(
+    code: &str,
+    args: Vec<TransactionArgument>,
+) -> (Program, Vec<VerifiedModule>, Vec<CompiledModule>) {
+    compile_program_with_address_return_deps(&default_sender(), code, args)
+}
+
+/// Like `compile_program`, but returns the parser or compiler error instead of panicking.
+pub fn try_compile_program(code: &str, args: Vec<TransactionArgument>) -> Result<Program> {
+    let compiler = Compiler::new(default_sender(), code);
+    compiler.into_program(args)
 }
 
 /// Compile the provided Move code into a blob which can be used as the code to be published
 /// (a Module).
 ///
-/// The code is compiled with the default account address (`0x0`).
+/// The code is compiled with the `default_sender` address.
 pub fn compile_module(code: &str) -> Vec<u8> {
-    let compiler = Compiler {
-        code,
-        ..Compiler::default()
-    };
-    compiler.into_module_blob().unwrap()
+    try_compile_module(code).unwrap()
+}
+
+/// Like `compile_module`, but returns the parser or compiler error instead of panicking.
+pub fn try_compile_module(code: &str) -> Result<Vec<u8>> {
+    let compiler = Compiler::new(default_sender(), code);
+    compiler.into_module_blob()
 }
 
 /// Compile the provided Move code into a blob which can be used as the code to be published
 /// (a Module).
 pub fn compile_module_with_address(address: &AccountAddress, code: &str) -> Vec<u8> {
-    let compiler = Compiler {
-        address: *address,
-        code,
-        ..Compiler::default()
-    };
+    let compiler = Compiler::new(*address, code);
+    compiler.into_module_blob().unwrap()
+}
+
+/// Compile the provided Move code into a blob which can be used as the code to be published
+/// (a Module), linking against `deps` in addition to the stdlib.
+///
+/// Every dependency has to pass the bytecode verifier.
+pub fn compile_module_with_deps(
+    address: &AccountAddress,
+    code: &str,
+    deps: Vec<CompiledModule>,
+) -> Vec<u8> {
+    let mut compiler = Compiler::new(*address, code);
+    compiler.extra_deps = verify_deps(deps);
     compiler.into_module_blob().unwrap()
 }
+
+/// Compile the provided Move code into a `CompiledModule`, e.g. to inspect it without
+/// deserializing a blob.
+///
+/// The code is compiled with the `default_sender` address.
+pub fn compile_module_return_compiled(code: &str) -> CompiledModule {
+    compile_module_with_address_return_compiled(&default_sender(), code)
+}
+
+/// Compile the provided Move code into a `CompiledModule` using `address` as its self address.
+pub fn compile_module_with_address_return_compiled(
+    address: &AccountAddress,
+    code: &str,
+) -> CompiledModule {
+    Compiler::new(*address, code)
+        .into_compiled_module()
+        .unwrap()
+}
+
+/// Deserialize the script and modules of `program`, e.g. to check that they match what was
+/// compiled.
+pub fn decompile_program(program: &Program) -> Result<(CompiledScript, Vec<CompiledModule>)> {
+    let script = CompiledScript::deserialize(program.code())
+        .map_err(|err| format_err!("script can't be deserialized: {}", err))?;
+    let modules = program
+        .modules()
+        .iter()
+        .enumerate()
+        .map(|(idx, blob)| {
+            CompiledModule::deserialize(blob)
+                .map_err(|err| format_err!("module {} can't be deserialized: {}", idx, err))
+        })
+        .collect::<Result<_>>()?;
+    Ok((script, modules))
+}
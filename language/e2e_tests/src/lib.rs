@@ -33,11 +33,7 @@ mod proptest_types;
 pub fn compile_and_execute(program: &str, args: Vec<TransactionArgument>) -> VMResult<()> {
     let address = AccountAddress::default();
     println!("{}", address);
-    let compiler = Compiler {
-        code: program,
-        address,
-        ..Compiler::default()
-    };
+    let compiler = Compiler::new(address, program);
     let compiled_program = compiler.into_compiled_program().expect("Failed to compile");
     let (verified_script, modules) =
         verify(&address, compiled_program.script, compiled_program.modules);
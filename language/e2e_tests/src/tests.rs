@@ -11,6 +11,7 @@
 
 mod account_universe;
 mod arithmetic;
+mod compile;
 mod create_account;
 mod function_call;
 mod genesis;
@@ -0,0 +1,191 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::compile::{
+    compile_module, compile_module_return_compiled, compile_module_with_address,
+    compile_module_with_address_return_compiled, compile_program_return_deps,
+    compile_program_with_address_with_deps, compile_script_with_deps, decompile_program,
+    set_default_sender, try_compile_module, try_compile_program, try_compile_script,
+};
+use types::{account_address::AccountAddress, transaction::Program};
+use vm::{
+    access::{ModuleAccess, ScriptAccess},
+    file_format::CompiledScript,
+    CompiledModule,
+};
+
+#[test]
+fn try_compile_reports_errors() {
+    let script = "
+        main() {
+            return;
+        }
+        ";
+    try_compile_script(script).unwrap();
+    try_compile_program(script, vec![]).unwrap();
+
+    let err = try_compile_script(
+        "
+        import 0x0.Missing;
+
+        main() {
+            return;
+        }
+        ",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "can't find module 0x{}.Missing in dependency list",
+            AccountAddress::default()
+        )
+    );
+
+    let err = try_compile_program(
+        "
+        main() {
+            let x: u64;
+            x = y;
+            return;
+        }
+        ",
+        vec![],
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "variable y undefined");
+
+    let err = try_compile_module("module M { public f() { N.g(); return; } }").unwrap_err();
+    assert_eq!(err.to_string(), "no module named N");
+}
+
+#[test]
+fn compile_with_every_supplied_dependency() {
+    let address = AccountAddress::new([1u8; 32]);
+    let compile_dep = |code: &str| {
+        let blob = compile_module_with_address(&address, code);
+        CompiledModule::deserialize(&blob).unwrap()
+    };
+    let deps = vec![
+        compile_dep("module A { public f() { return; } }"),
+        compile_dep("module B { public g() { return; } }"),
+    ];
+
+    let script = format!(
+        "
+        import 0x{}.B;
+
+        main() {{
+            B.g();
+            return;
+        }}
+        ",
+        address
+    );
+    let program = compile_program_with_address_with_deps(&address, &script, vec![], deps);
+    assert!(program.modules().is_empty());
+}
+
+#[test]
+fn compile_script_against_supplied_dependency() {
+    let address = AccountAddress::new([1u8; 32]);
+    let blob = compile_module_with_address(&address, "module A { public f() { return; } }");
+    let dep = CompiledModule::deserialize(&blob).unwrap();
+
+    let script = format!(
+        "
+        import 0x{}.A;
+
+        main() {{
+            A.f();
+            return;
+        }}
+        ",
+        address
+    );
+    let blob = compile_script_with_deps(&address, &script, vec![dep]);
+    let script = CompiledScript::deserialize(&blob).unwrap();
+    assert!(script
+        .module_handles()
+        .iter()
+        .any(|handle| script.string_at(handle.name) == "A"));
+}
+
+#[test]
+fn returned_modules_feed_later_compilations() {
+    let (program, _, modules) = compile_program_return_deps(
+        "
+        modules:
+        module A {
+            public f() { return; }
+        }
+        script:
+        main() {
+            return;
+        }
+        ",
+        vec![],
+    );
+    assert_eq!(program.modules().len(), 1);
+    assert_eq!(modules.len(), 1);
+
+    let address = AccountAddress::default();
+    let script = format!(
+        "
+        import 0x{}.A;
+
+        main() {{
+            A.f();
+            return;
+        }}
+        ",
+        address
+    );
+    compile_program_with_address_with_deps(&address, &script, vec![], modules);
+}
+
+#[test]
+fn default_sender_sets_module_address() {
+    let address = AccountAddress::new([3u8; 32]);
+    set_default_sender(address);
+    let blob = compile_module("module M { public f() { return; } }");
+    set_default_sender(AccountAddress::default());
+
+    let module = CompiledModule::deserialize(&blob).unwrap();
+    assert_eq!(module.self_id().address(), &address);
+}
+
+#[test]
+fn compiled_module_has_requested_address() {
+    let code = "module M { public f() { return; } }";
+    let address = AccountAddress::new([4u8; 32]);
+    let module = compile_module_with_address_return_compiled(&address, code);
+    assert_eq!(module.self_id().address(), &address);
+    assert_eq!(
+        compile_module_return_compiled(code).self_id().address(),
+        &AccountAddress::default()
+    );
+}
+
+#[test]
+fn decompile_compiled_program() {
+    let code = "
+        modules:
+        module M {
+            public f() { return; }
+        }
+        script:
+        main() {
+            return;
+        }
+        ";
+    let (program, _, modules) = compile_program_return_deps(code, vec![]);
+    let (script, decompiled_modules) = decompile_program(&program).unwrap();
+    assert_eq!(decompiled_modules, modules);
+    let mut script_blob = vec![];
+    script.serialize(&mut script_blob).unwrap();
+    assert_eq!(script_blob.as_slice(), program.code());
+
+    let truncated = Program::new(program.code()[1..].to_vec(), vec![], vec![]);
+    assert!(decompile_program(&truncated).is_err());
+}
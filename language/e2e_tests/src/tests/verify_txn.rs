@@ -544,10 +544,7 @@ fn test_dependency_fails_verification() {
         }
     }
     ";
-    let compiler = Compiler {
-        code: bad_module_code,
-        ..Compiler::default()
-    };
+    let compiler = Compiler::new(AccountAddress::default(), bad_module_code);
     let module = compiler.into_compiled_module().expect("Failed to compile");
     executor.add_module(&module.self_id(), &module);
 
@@ -565,15 +562,11 @@ fn test_dependency_fails_verification() {
     }
     ";
 
-    let compiler = Compiler {
-        code,
-        address: *sender.address(),
-        // This is OK because we *know* the module is unverified.
-        extra_deps: vec![VerifiedModule::bypass_verifier_DANGEROUS_FOR_TESTING_ONLY(
-            module,
-        )],
-        ..Compiler::default()
-    };
+    let mut compiler = Compiler::new(*sender.address(), code);
+    // This is OK because we *know* the module is unverified.
+    compiler.extra_deps = vec![VerifiedModule::bypass_verifier_DANGEROUS_FOR_TESTING_ONLY(
+        module,
+    )];
     let script = compiler.into_script_blob().expect("Failed to compile");
     let txn = sender.account().create_signed_txn(
         TransactionPayload::Script(Script::new(script, vec![])),
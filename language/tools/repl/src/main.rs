@@ -97,12 +97,8 @@ impl Repl {
         max_gas_amount: u64,
         gas_unit_price: u64,
     ) -> SignedTransaction {
-        let compiler = Compiler {
-            code: &program_str,
-            address: sender_address,
-            extra_deps: self.modules.clone(),
-            ..Compiler::default()
-        };
+        let mut compiler = Compiler::new(sender_address, &program_str);
+        compiler.extra_deps = self.modules.clone();
         let compiled_program = compiler.into_compiled_program().expect("Failed to compile");
 
         let (verified_script, to_be_published_modules) = static_verify_program(
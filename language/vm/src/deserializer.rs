@@ -98,8 +98,8 @@ fn check_binary(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<u8> {
     } else {
         return Err(BinaryError::Malformed);
     }
-    let major_ver = 1u8;
-    let minor_ver = 0u8;
+    let major_ver = BinaryConstants::VERSION_MAJOR;
+    let minor_ver = BinaryConstants::VERSION_MINOR;
     if let Ok(ver) = cursor.read_u8() {
         if ver != major_ver {
             return Err(BinaryError::UnknownVersion);
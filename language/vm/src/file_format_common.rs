@@ -23,6 +23,10 @@ impl BinaryConstants {
     pub const LIBRA_MAGIC_SIZE: usize = 8;
     pub const LIBRA_MAGIC: [u8; BinaryConstants::LIBRA_MAGIC_SIZE] =
         [b'L', b'I', b'B', b'R', b'A', b'V', b'M', b'\n'];
+    /// The version of the binary format that is written by the serializer and accepted by the
+    /// deserializer.
+    pub const VERSION_MAJOR: u8 = 1;
+    pub const VERSION_MINOR: u8 = 0;
     /// The `LIBRA_MAGIC` size, 1 byte for major version, 1 byte for minor version and 1 byte
     /// for table count.
     pub const HEADER_SIZE: usize = BinaryConstants::LIBRA_MAGIC_SIZE + 3;
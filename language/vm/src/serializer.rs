@@ -18,6 +18,13 @@ impl CompiledScript {
     pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
         self.as_inner().serialize(binary)
     }
+
+    /// Like `serialize`, but writes `major_version` into the header rather than the current
+    /// version. See [`CompiledScriptMut::serialize_with_version`].
+    pub fn serialize_with_version(&self, binary: &mut Vec<u8>, major_version: u8) -> Result<()> {
+        self.as_inner()
+            .serialize_with_version(binary, major_version)
+    }
 }
 
 impl CompiledScriptMut {
@@ -26,8 +33,17 @@ impl CompiledScriptMut {
     /// This is intended mainly for test code. Production code will typically use
     /// [`CompiledScript::serialize`].
     pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
+        self.serialize_with_version(binary, BinaryConstants::VERSION_MAJOR)
+    }
+
+    /// Serializes this into a binary format, with `major_version` in the header.
+    ///
+    /// Only the current version of the format is implemented, so the tables are laid out the
+    /// same whatever the version. This is meant for testing how readers handle binaries of
+    /// other versions; the deserializer in this crate rejects them.
+    pub fn serialize_with_version(&self, binary: &mut Vec<u8>, major_version: u8) -> Result<()> {
         let mut binary_data = BinaryData::from(binary.clone());
-        let mut ser = ScriptSerializer::new(1, 0);
+        let mut ser = ScriptSerializer::new(major_version, BinaryConstants::VERSION_MINOR);
         let mut temp = BinaryData::new();
         ser.serialize(&mut temp, self)?;
         ser.serialize_header(&mut binary_data)?;
@@ -43,6 +59,13 @@ impl CompiledModule {
     pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
         self.as_inner().serialize(binary)
     }
+
+    /// Like `serialize`, but writes `major_version` into the header rather than the current
+    /// version. See [`CompiledModuleMut::serialize_with_version`].
+    pub fn serialize_with_version(&self, binary: &mut Vec<u8>, major_version: u8) -> Result<()> {
+        self.as_inner()
+            .serialize_with_version(binary, major_version)
+    }
 }
 
 impl CompiledModuleMut {
@@ -51,8 +74,17 @@ impl CompiledModuleMut {
     /// This is intended mainly for test code. Production code will typically use
     /// [`CompiledModule::serialize`].
     pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
+        self.serialize_with_version(binary, BinaryConstants::VERSION_MAJOR)
+    }
+
+    /// Serializes this into a binary format, with `major_version` in the header.
+    ///
+    /// Only the current version of the format is implemented, so the tables are laid out the
+    /// same whatever the version. This is meant for testing how readers handle binaries of
+    /// other versions; the deserializer in this crate rejects them.
+    pub fn serialize_with_version(&self, binary: &mut Vec<u8>, major_version: u8) -> Result<()> {
         let mut binary_data = BinaryData::from(binary.clone());
-        let mut ser = ModuleSerializer::new(1, 0);
+        let mut ser = ModuleSerializer::new(major_version, BinaryConstants::VERSION_MINOR);
         let mut temp = BinaryData::new();
         ser.serialize(&mut temp, self)?;
         ser.serialize_header(&mut binary_data)?;
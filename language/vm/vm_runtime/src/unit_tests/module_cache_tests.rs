@@ -500,11 +500,8 @@ fn test_multi_level_cache_write_back() {
 }
 
 fn parse_and_compile_modules(s: impl AsRef<str>) -> Vec<CompiledModule> {
-    let compiler = Compiler {
-        code: s.as_ref(),
-        skip_stdlib_deps: true,
-        ..Compiler::default()
-    };
+    let mut compiler = Compiler::new(AccountAddress::default(), s.as_ref());
+    compiler.skip_stdlib_deps = true;
     compiler
         .into_compiled_program()
         .expect("Failed to compile program")
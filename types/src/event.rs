@@ -1,11 +1,9 @@
 #![allow(clippy::unit_arg)]
 
-#[cfg(any(test, feature = "testing"))]
 use crate::account_address::AccountAddress;
-#[cfg(any(test, feature = "testing"))]
-use canonical_serialization::SimpleSerializer;
 use canonical_serialization::{
     CanonicalDeserialize, CanonicalDeserializer, CanonicalSerialize, CanonicalSerializer,
+    SimpleSerializer,
 };
 #[cfg(any(test, feature = "testing"))]
 use crypto::HashValue;
@@ -14,18 +12,28 @@ use hex;
 #[cfg(any(test, feature = "testing"))]
 use proptest_derive::Arbitrary;
 use proto_conv::{FromProto, IntoProto};
-use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, fmt};
-#[cfg(any(test, feature = "testing"))]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{btree_set, BTreeMap, BTreeSet, HashMap, VecDeque},
+    convert::TryFrom,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    iter::FromIterator,
+    marker::PhantomData,
+    str::FromStr,
+    sync::mpsc::Sender,
+};
 use tiny_keccak::sha3_256;
 
 /// Size of an event key.
+///
+/// Keys derived by `EventKey::new_from_address` are SHA3-256 hashes, so this has to be the hash
+/// length; the compiler rejects any other value. Keys don't embed an address followed by a
+/// counter, so this isn't tied to `ADDRESS_LENGTH`, and there is no counter offset to speak of.
 pub const EVENT_KEY_LENGTH: usize = 32;
 
 /// A struct that represents a globally unique id for an Event stream that a user can listen to.
-#[derive(
-    Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Clone, Serialize, Deserialize, Copy,
-)]
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Default, Clone, Serialize, Deserialize, Copy)]
 #[cfg_attr(any(test, feature = "testing"), derive(Arbitrary))]
 pub struct EventKey([u8; EVENT_KEY_LENGTH]);
 
@@ -35,31 +43,186 @@ impl EventKey {
         EventKey(key)
     }
 
+    /// The all-zero key, which marks an event stream that hasn't been assigned a key yet. This is
+    /// the same as `EventKey::default()`.
+    pub const fn genesis() -> Self {
+        EventKey([0; EVENT_KEY_LENGTH])
+    }
+
+    /// Returns whether this is the all-zero `genesis` key.
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+
+    /// Compares two keys in time independent of where they differ. Prefer this over `==` when
+    /// comparing against a key that gates access to an event stream, so the comparison doesn't
+    /// leak how long a matching prefix a guess has.
+    pub fn ct_eq(&self, other: &EventKey) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+
     /// Get the byte representation of the event key.
     pub fn as_bytes(&self) -> &[u8] {
         &self.0
     }
 
+    /// Get the fixed-size byte array of the event key.
+    pub fn as_array(&self) -> &[u8; EVENT_KEY_LENGTH] {
+        &self.0
+    }
+
+    /// Convert event key into its fixed-size byte array.
+    pub fn into_array(self) -> [u8; EVENT_KEY_LENGTH] {
+        self.0
+    }
+
+    /// Get the first `n` bytes of the key, e.g. to shard event streams by. Fails if `n` is
+    /// larger than `EVENT_KEY_LENGTH`.
+    pub fn prefix(&self, n: usize) -> Result<&[u8]> {
+        ensure!(
+            n <= EVENT_KEY_LENGTH,
+            "Event key prefix of {} bytes requested, keys have {}",
+            n,
+            EVENT_KEY_LENGTH
+        );
+        Ok(&self.0[..n])
+    }
+
+    /// Map the key to one of `num_shards` shards by its leading 8 bytes, read as a big-endian
+    /// `u64`. Keys are hashes, so they spread evenly across the shards. Fails if `num_shards` is
+    /// zero.
+    pub fn shard(&self, num_shards: u16) -> Result<u16> {
+        ensure!(num_shards > 0, "Event keys can't be sharded into 0 shards");
+        let mut leading = [0u8; 8];
+        leading.copy_from_slice(&self.0[..8]);
+        Ok((u64::from_be_bytes(leading) % u64::from(num_shards)) as u16)
+    }
+
     /// Convert event key into a byte array.
     pub fn to_vec(&self) -> Vec<u8> {
         self.0.to_vec()
     }
 
+    /// Convert an owned byte vector into an event key, failing unless it is exactly
+    /// `EVENT_KEY_LENGTH` bytes long.
+    pub fn try_from_vec(bytes: Vec<u8>) -> Result<Self> {
+        EventKey::try_from(bytes.as_slice())
+    }
+
+    /// Parse a key from its hex encoding, as rendered by `LowerHex` or, with a `0x` prefix, by
+    /// `Display`.
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let hex_str = if s.starts_with("0x") { &s[2..] } else { s };
+        let bytes = hex::decode(hex_str)?;
+        ensure!(
+            bytes.len() == EVENT_KEY_LENGTH,
+            "Event key {} has {} bytes, expected {}",
+            s,
+            bytes.len(),
+            EVENT_KEY_LENGTH
+        );
+        EventKey::try_from(bytes.as_slice())
+    }
+
+    /// Serialize the key followed by a checksum byte, the XOR of all key bytes. Any single
+    /// corrupted byte changes the checksum, so `from_checked_bytes` rejects it.
+    pub fn with_checksum(&self) -> [u8; EVENT_KEY_LENGTH + 1] {
+        let mut bytes = [0u8; EVENT_KEY_LENGTH + 1];
+        bytes[..EVENT_KEY_LENGTH].copy_from_slice(&self.0);
+        bytes[EVENT_KEY_LENGTH] = checksum(&self.0);
+        bytes
+    }
+
+    /// Parse the output of `with_checksum`, failing if the checksum doesn't match.
+    pub fn from_checked_bytes(bytes: &[u8; EVENT_KEY_LENGTH + 1]) -> Result<EventKey> {
+        let (key, expected) = bytes.split_at(EVENT_KEY_LENGTH);
+        ensure!(
+            checksum(key) == expected[0],
+            "Checksum mismatch for event key {}",
+            hex::encode(key)
+        );
+        EventKey::try_from(key)
+    }
+
+    /// Map this key to a pseudonym under `secret`. The same key and secret always give the same
+    /// pseudonym, but without the secret the pseudonym can't be linked back to the key.
+    ///
+    /// The pseudonym is the SHA3-256 hash of the length-prefixed secret followed by the key.
+    /// Unlike SHA-2, SHA3 isn't subject to length extension, so prefixing the secret is enough to
+    /// make this a keyed hash.
+    pub fn pseudonymize(&self, secret: &[u8]) -> EventKey {
+        let mut serializer: SimpleSerializer<Vec<u8>> = SimpleSerializer::new();
+        serializer
+            .encode_bytes(secret)
+            .expect("Can't serialize secret");
+        serializer
+            .encode_struct(self)
+            .expect("Can't serialize event key");
+        EventKey(sha3_256(&serializer.get_output()))
+    }
+
+    /// Hash this key to 64 bits with FNV-1a. Unlike the `Hash` impl, which is fed to randomly
+    /// seeded hashers, the result is the same in every process, so it can be used for sharding
+    /// and sampling decisions that have to be reproducible. It isn't a cryptographic hash.
+    pub fn stable_hash64(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+        self.0.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        })
+    }
+
     #[cfg(any(test, feature = "testing"))]
     /// Create a random event key for testing
     pub fn random() -> Self {
         EventKey::try_from(HashValue::random().to_vec().as_slice()).unwrap()
     }
 
-    #[cfg(any(test, feature = "testing"))]
-    /// Create a unique handle by using an AccountAddress and a counter.
+    /// Derive a unique key from an AccountAddress and a salt. The derivation is deterministic,
+    /// so the key of an account's event stream can be computed from its address and salt.
+    ///
+    /// The key is a hash, so neither the address nor the salt can be recovered from it. Keys
+    /// aren't laid out as the address followed by the salt either: an `EVENT_KEY_LENGTH` key has
+    /// no room for both.
     pub fn new_from_address(addr: &AccountAddress, salt: u64) -> Self {
+        Self::try_new_from_address(addr, salt).expect("Can't serialize address and salt")
+    }
+
+    /// Like `new_from_address`, but returns an error rather than panicking if the address and
+    /// salt can't be serialized.
+    pub fn try_new_from_address(addr: &AccountAddress, salt: u64) -> Result<Self> {
         let mut serializer: SimpleSerializer<Vec<u8>> = SimpleSerializer::new();
-        serializer.encode_u64(salt).expect("Can't serialize salt");
-        serializer
-            .encode_struct(addr)
-            .expect("Can't serialize address");
-        EventKey(sha3_256(&serializer.get_output()))
+        serializer.encode_u64(salt)?;
+        serializer.encode_struct(addr)?;
+        Ok(EventKey(sha3_256(&serializer.get_output())))
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |acc, byte| acc ^ byte)
+}
+
+impl FromStr for EventKey {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        EventKey::from_hex(s)
+    }
+}
+
+impl From<[u8; EVENT_KEY_LENGTH]> for EventKey {
+    fn from(bytes: [u8; EVENT_KEY_LENGTH]) -> Self {
+        EventKey(bytes)
+    }
+}
+
+impl From<EventKey> for [u8; EVENT_KEY_LENGTH] {
+    fn from(key: EventKey) -> Self {
+        key.0
     }
 }
 
@@ -70,8 +233,9 @@ impl TryFrom<&[u8]> for EventKey {
     fn try_from(bytes: &[u8]) -> Result<EventKey> {
         ensure!(
             bytes.len() == EVENT_KEY_LENGTH,
-            "The Address {:?} is of invalid length",
-            bytes
+            "Event key has {} bytes, expected {}",
+            bytes.len(),
+            EVENT_KEY_LENGTH
         );
         let mut addr = [0u8; EVENT_KEY_LENGTH];
         addr.copy_from_slice(bytes);
@@ -79,8 +243,149 @@ impl TryFrom<&[u8]> for EventKey {
     }
 }
 
+/// An ordered set of event keys, used to reconcile event indexes against each other.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EventKeySet(BTreeSet<EventKey>);
+
+impl EventKeySet {
+    /// Constructs an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` to the set, returning whether it wasn't present yet.
+    pub fn insert(&mut self, key: EventKey) -> bool {
+        self.0.insert(key)
+    }
+
+    /// Returns whether `key` is in the set.
+    pub fn contains(&self, key: &EventKey) -> bool {
+        self.0.contains(key)
+    }
+
+    /// Returns the number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> btree_set::Iter<EventKey> {
+        self.0.iter()
+    }
+
+    /// Returns the keys that are in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.0.difference(&other.0).copied().collect()
+    }
+
+    /// Returns the keys that are in both `self` and `other`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.0.intersection(&other.0).copied().collect()
+    }
+
+    /// Returns the keys that are in either `self` or `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        self.0.union(&other.0).copied().collect()
+    }
+}
+
+impl FromIterator<EventKey> for EventKeySet {
+    fn from_iter<I: IntoIterator<Item = EventKey>>(iter: I) -> Self {
+        EventKeySet(iter.into_iter().collect())
+    }
+}
+
+/// Tracks which positions of each event stream have been seen, e.g. processed by an indexer.
+///
+/// The positions of a stream are kept sorted and stored as LEB128 varints of the gaps between
+/// them, so sparse sets of large counts stay small.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CountIndex {
+    streams: BTreeMap<EventKey, Vec<u8>>,
+}
+
+impl CountIndex {
+    /// Constructs an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that position `count` of stream `key` has been seen. Returns whether it wasn't
+    /// recorded already.
+    pub fn mark_seen(&mut self, key: EventKey, count: u64) -> bool {
+        let encoded = self.streams.entry(key).or_insert_with(Vec::new);
+        let mut counts = decode_deltas(encoded);
+        match counts.binary_search(&count) {
+            Ok(_) => false,
+            Err(pos) => {
+                counts.insert(pos, count);
+                *encoded = encode_deltas(&counts);
+                true
+            }
+        }
+    }
+
+    /// Returns whether position `count` of stream `key` has been seen.
+    pub fn is_seen(&self, key: &EventKey, count: u64) -> bool {
+        self.streams
+            .get(key)
+            .map_or(false, |encoded| decode_deltas(encoded).contains(&count))
+    }
+
+    /// Returns the number of bytes used to store the positions of all streams.
+    pub fn encoded_size(&self) -> usize {
+        self.streams.values().map(Vec::len).sum()
+    }
+}
+
+fn encode_deltas(sorted: &[u64]) -> Vec<u8> {
+    let mut encoded = vec![];
+    let mut prev = 0;
+    for count in sorted {
+        let mut delta = count - prev;
+        prev = *count;
+        loop {
+            let byte = (delta & 0x7f) as u8;
+            delta >>= 7;
+            if delta == 0 {
+                encoded.push(byte);
+                break;
+            }
+            encoded.push(byte | 0x80);
+        }
+    }
+    encoded
+}
+
+fn decode_deltas(encoded: &[u8]) -> Vec<u64> {
+    let mut counts = vec![];
+    let mut prev = 0;
+    let mut delta = 0;
+    let mut shift = 0;
+    for byte in encoded {
+        delta |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            prev += delta;
+            counts.push(prev);
+            delta = 0;
+            shift = 0;
+        }
+    }
+    counts
+}
+
+/// Version tag of the format produced by `EventHandle::checkpoint`.
+const CHECKPOINT_VERSION: u8 = 1;
+
 /// A Rust representation of an Event Handle Resource.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(any(test, feature = "testing"), derive(Arbitrary))]
 pub struct EventHandle {
     /// The associated globally unique key that is used as the key to the EventStore.
     key: EventKey,
@@ -103,6 +408,152 @@ impl EventHandle {
         self.count
     }
 
+    /// Advance the counter past one emitted event, as the Move `EventHandle` resource does.
+    pub fn increment(&mut self) {
+        self.count += 1;
+    }
+
+    /// Return a mutable reference to the counter, e.g. to advance it past several events at once.
+    pub fn count_mut(&mut self) -> &mut u64 {
+        &mut self.count
+    }
+
+    /// Derive the key naming the event at position `seq` of this stream.
+    ///
+    /// On chain an event is addressed by the pair (stream key, sequence number). The derived key
+    /// packs that pair into a single `EventKey`, for use where a flat key is needed (sets, maps).
+    pub fn event_key_at(&self, seq: u64) -> EventKey {
+        derive_event_key(&self.key, seq)
+    }
+
+    /// Return the derived key of the first event of this stream (see `event_key_at`). It only
+    /// depends on the stream key, so it identifies the stream whatever the handle's count.
+    pub fn genesis_key(&self) -> EventKey {
+        self.event_key_at(0)
+    }
+
+    /// Enumerate the events emitted so far as the pairs they are addressed by on chain, (sequence
+    /// number, stream key), for sequence numbers `0..count`.
+    ///
+    /// The key is the stream key, the same for every event; only the sequence number changes.
+    /// Use `event_key_at` instead where each event needs a key of its own, e.g. to look it up in
+    /// a set.
+    pub fn iter_keys(&self) -> impl Iterator<Item = (u64, EventKey)> {
+        let key = self.key;
+        (0..self.count).map(move |seq| (seq, key))
+    }
+
+    /// Return the positions in `0..count` whose derived event keys (see `event_key_at`) are
+    /// absent from `received`, in ascending order.
+    pub fn find_gaps(&self, received: &BTreeSet<EventKey>) -> Vec<u64> {
+        (0..self.count)
+            .filter(|seq| !received.contains(&self.event_key_at(*seq)))
+            .collect()
+    }
+
+    /// Return the window that the event at position `index` falls into, when the stream is cut
+    /// into consecutive windows of `window_size` events. Fails if `window_size` is zero.
+    pub fn window_slot(&self, index: u64, window_size: u64) -> Result<u64> {
+        ensure!(
+            window_size > 0,
+            "Event streams can't be cut into windows of 0 events"
+        );
+        Ok(index / window_size)
+    }
+
+    /// Group the derived keys (see `event_key_at`) of the events emitted so far by window (see
+    /// `window_slot`). Keys are in stream order within each window. Fails if `window_size` is
+    /// zero, even if no events were emitted yet.
+    pub fn keys_by_window(&self, window_size: u64) -> Result<BTreeMap<u64, Vec<EventKey>>> {
+        ensure!(
+            window_size > 0,
+            "Event streams can't be cut into windows of 0 events"
+        );
+        let mut windows = BTreeMap::new();
+        for seq in 0..self.count {
+            windows
+                .entry(seq / window_size)
+                .or_insert_with(Vec::new)
+                .push(self.event_key_at(seq));
+        }
+        Ok(windows)
+    }
+
+    /// Reserve the next `n` positions of this stream, advancing the count past them, so that
+    /// their keys are known before the payloads are.
+    pub fn reserve(&mut self, n: u64) -> Reservation {
+        let start = self.count;
+        self.count += n;
+        Reservation {
+            start,
+            keys: (start..self.count)
+                .map(|seq| self.event_key_at(seq))
+                .collect(),
+        }
+    }
+
+    /// Walk the events emitted so far in stream order, fetching the payload for each derived key
+    /// (see `event_key_at`) with `fetch` and handing it to `visitor`.
+    pub fn replay<V: EventVisitor>(
+        &self,
+        fetch: impl Fn(&EventKey) -> Option<Vec<u8>>,
+        visitor: &mut V,
+    ) {
+        for seq in 0..self.count {
+            let key = self.event_key_at(seq);
+            let payload = fetch(&key);
+            visitor.visit(seq, &key, payload);
+        }
+    }
+
+    /// Attach `sink` to this handle, so that the key of every event emitted through the returned
+    /// wrapper is also sent on the channel.
+    pub fn with_sink(self, sink: Sender<EventKey>) -> NotifyingEventHandle {
+        NotifyingEventHandle { handle: self, sink }
+    }
+
+    /// Return the length of the canonical serialization of this handle.
+    pub fn serialized_size(&self) -> usize {
+        SimpleSerializer::<Vec<u8>>::serialize(self)
+            .expect("Can't serialize event handle")
+            .len()
+    }
+
+    /// Save the position of this handle for crash recovery.
+    ///
+    /// The layout is `version (u8) | key length (u8) | key | count (u64, little endian)`. It is
+    /// independent of the canonical serialization, so it stays readable if the on-chain layout
+    /// changes.
+    pub fn checkpoint(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + EVENT_KEY_LENGTH + 8);
+        bytes.push(CHECKPOINT_VERSION);
+        bytes.push(EVENT_KEY_LENGTH as u8);
+        bytes.extend_from_slice(self.key.as_bytes());
+        bytes.extend_from_slice(&self.count.to_le_bytes());
+        bytes
+    }
+
+    /// Restore a handle from the output of `checkpoint`.
+    pub fn restore(bytes: &[u8]) -> Result<EventHandle> {
+        ensure!(bytes.len() >= 2, "Checkpoint is too short to hold a header");
+        ensure!(
+            bytes[0] == CHECKPOINT_VERSION,
+            "Unsupported checkpoint version {}",
+            bytes[0]
+        );
+        let key_len = bytes[1] as usize;
+        ensure!(
+            bytes.len() == 2 + key_len + 8,
+            "Checkpoint length {} doesn't match key length {}",
+            bytes.len(),
+            key_len
+        );
+        let key = EventKey::try_from(&bytes[2..2 + key_len])?;
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&bytes[2 + key_len..]);
+        Ok(EventHandle::new(key, u64::from_le_bytes(count)))
+    }
+
     #[cfg(any(test, feature = "testing"))]
     /// Create a random event handle for testing
     pub fn random_handle(count: u64) -> Self {
@@ -112,8 +563,9 @@ impl EventHandle {
         }
     }
 
-    #[cfg(any(test, feature = "testing"))]
-    /// Derive a unique handle by using an AccountAddress and a counter.
+    /// Derive a unique handle by using an AccountAddress and a counter, see
+    /// `EventKey::new_from_address`. The handle starts out with no events emitted, i.e. a count
+    /// of 0.
     pub fn new_from_address(addr: &AccountAddress, salt: u64) -> Self {
         Self {
             key: EventKey::new_from_address(addr, salt),
@@ -122,12 +574,353 @@ impl EventHandle {
     }
 }
 
+/// Return the total length of the canonical serializations of `handles` (see
+/// `EventHandle::serialized_size`), e.g. to estimate the storage an account state dump needs.
+pub fn total_serialized_size(handles: &[EventHandle]) -> usize {
+    handles.iter().map(EventHandle::serialized_size).sum()
+}
+
+/// The event streams an indexer has seen and how many events of each, exchanged between indexers
+/// to find out which events one of them is missing. See `local_summary`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSummary {
+    counts: BTreeMap<EventKey, u64>,
+}
+
+impl SyncSummary {
+    /// Return the number of events seen on the stream `key`, if the stream is known at all.
+    pub fn count(&self, key: &EventKey) -> Option<u64> {
+        self.counts.get(key).cloned()
+    }
+
+    /// Return, for every stream with events the `remote` summary hasn't seen, the key of the
+    /// stream and the range `start..end` of positions the remote must fetch, in key order.
+    pub fn compute_delta(&self, remote: &SyncSummary) -> Vec<(EventKey, u64, u64)> {
+        self.counts
+            .iter()
+            .filter_map(|(key, count)| {
+                let remote_count = remote.count(key).unwrap_or(0);
+                if remote_count < *count {
+                    Some((*key, remote_count, *count))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Summarize `handles` for syncing with another indexer. If several handles share a key, the
+/// highest count is kept.
+pub fn local_summary(handles: &[EventHandle]) -> SyncSummary {
+    let mut counts = BTreeMap::new();
+    for handle in handles {
+        let count = counts.entry(handle.key).or_insert(0);
+        *count = handle.count.max(*count);
+    }
+    SyncSummary { counts }
+}
+
+fn derive_event_key(stream_key: &EventKey, seq: u64) -> EventKey {
+    let mut serializer: SimpleSerializer<Vec<u8>> = SimpleSerializer::new();
+    serializer
+        .encode_u64(seq)
+        .expect("Can't serialize sequence number");
+    serializer
+        .encode_struct(stream_key)
+        .expect("Can't serialize event key");
+    EventKey(sha3_256(&serializer.get_output()))
+}
+
+/// Keeps the positions of the most recent events of a stream, up to a fixed capacity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventRing {
+    key: EventKey,
+    capacity: usize,
+    recent: VecDeque<u64>,
+}
+
+impl EventRing {
+    /// Constructs an empty ring for the stream `key` that retains at most `capacity` events.
+    pub fn new(key: EventKey, capacity: usize) -> Self {
+        EventRing {
+            key,
+            capacity,
+            recent: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records the event at position `count`, dropping the oldest one if the ring is full.
+    pub fn record(&mut self, count: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(count);
+    }
+
+    /// Returns the derived keys (see `EventHandle::event_key_at`) of the retained events, oldest
+    /// first.
+    pub fn recent_keys(&self) -> Vec<EventKey> {
+        self.recent
+            .iter()
+            .map(|seq| derive_event_key(&self.key, *seq))
+            .collect()
+    }
+}
+
+/// Receives the events of a stream replayed by `EventHandle::replay`.
+pub trait EventVisitor {
+    /// Called for the event at position `index`, with the payload the fetcher returned for it.
+    /// `payload` is `None` if the event couldn't be fetched.
+    fn visit(&mut self, index: u64, key: &EventKey, payload: Option<Vec<u8>>);
+}
+
+/// A batch of consecutive positions of an event stream, taken by `EventHandle::reserve`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reservation {
+    start: u64,
+    keys: Vec<EventKey>,
+}
+
+impl Reservation {
+    /// Return the position of the first reserved event.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Return the derived keys of the reserved events, in stream order.
+    pub fn keys(&self) -> &[EventKey] {
+        &self.keys
+    }
+}
+
+/// An `EventHandle` that only accepts events of type `T`, so that a stream can't accidentally be
+/// fed events of the wrong type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventEmitter<T> {
+    handle: EventHandle,
+    phantom: PhantomData<T>,
+}
+
+impl<T: CanonicalSerialize> EventEmitter<T> {
+    /// Binds `handle` to events of type `T`.
+    pub fn new(handle: EventHandle) -> Self {
+        EventEmitter {
+            handle,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Return the underlying handle.
+    pub fn handle(&self) -> &EventHandle {
+        &self.handle
+    }
+
+    /// Unbind the underlying handle.
+    pub fn into_handle(self) -> EventHandle {
+        self.handle
+    }
+
+    /// Serialize `event` and advance the handle, returning the key of the emitted event along
+    /// with its serialized payload.
+    pub fn emit(&mut self, event: T) -> Result<(EventKey, Vec<u8>)> {
+        let payload = SimpleSerializer::<Vec<u8>>::serialize(&event)?;
+        let key = self.handle.event_key_at(self.handle.count);
+        self.handle.count += 1;
+        Ok((key, payload))
+    }
+}
+
+/// An `EventHandle` that multiplexes several typed streams, told apart by a type index, under
+/// the key of the handle.
+///
+/// The per-type counters are local bookkeeping rather than part of the on-chain `EventHandle`
+/// resource, which is why they are kept here: a new emitter starts every type's sequence at zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedEventEmitter {
+    handle: EventHandle,
+    type_counts: BTreeMap<u8, u64>,
+}
+
+impl TypedEventEmitter {
+    /// Multiplexes typed streams onto `handle`.
+    pub fn new(handle: EventHandle) -> Self {
+        TypedEventEmitter {
+            handle,
+            type_counts: BTreeMap::new(),
+        }
+    }
+
+    /// Return the underlying handle.
+    pub fn handle(&self) -> &EventHandle {
+        &self.handle
+    }
+
+    /// Detach the underlying handle, dropping the per-type counters.
+    pub fn into_handle(self) -> EventHandle {
+        self.handle
+    }
+
+    /// Return the number of events of type `type_index` emitted so far.
+    pub fn type_count(&self, type_index: u8) -> u64 {
+        self.type_counts.get(&type_index).cloned().unwrap_or(0)
+    }
+
+    /// Emit the next event of type `type_index` and return its key.
+    ///
+    /// Every type has its own counter, so the events of each type form an independent sequence
+    /// under the shared stream key. The key is derived from the stream key, the type and the
+    /// position within that type's sequence. The handle advances past every emitted event,
+    /// whatever its type, so its count is the total number of events in the stream.
+    pub fn emit(&mut self, type_index: u8) -> EventKey {
+        let type_count = self.type_counts.entry(type_index).or_insert(0);
+        let seq = *type_count;
+        *type_count += 1;
+        self.handle.count += 1;
+
+        let mut serializer: SimpleSerializer<Vec<u8>> = SimpleSerializer::new();
+        serializer
+            .encode_u8(type_index)
+            .expect("Can't serialize type index");
+        serializer
+            .encode_u64(seq)
+            .expect("Can't serialize sequence number");
+        serializer
+            .encode_struct(&self.handle.key)
+            .expect("Can't serialize event key");
+        EventKey(sha3_256(&serializer.get_output()))
+    }
+}
+
+/// An event key tagged with the time after which its stream is no longer of interest, so that
+/// relays can sweep transient subscriptions. `expires_at` is in whatever unit the caller measures
+/// `now` in, e.g. seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct ExpiringEventKey {
+    pub key: EventKey,
+    pub expires_at: u64,
+}
+
+impl ExpiringEventKey {
+    pub fn new(key: EventKey, expires_at: u64) -> Self {
+        ExpiringEventKey { key, expires_at }
+    }
+
+    /// Returns whether the key has expired by `now`. A key expires at `expires_at`, inclusive.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+/// Remove the keys that have expired by `now` (see `ExpiringEventKey::is_expired`), keeping the
+/// order of the remaining ones.
+pub fn sweep_expired(keys: &mut Vec<ExpiringEventKey>, now: u64) {
+    keys.retain(|key| !key.is_expired(now));
+}
+
+/// An `EventHandle` that sends the key of every event it emits on a channel, see
+/// `EventHandle::with_sink`.
+#[derive(Debug, Clone)]
+pub struct NotifyingEventHandle {
+    handle: EventHandle,
+    sink: Sender<EventKey>,
+}
+
+impl NotifyingEventHandle {
+    /// Return the underlying handle.
+    pub fn handle(&self) -> &EventHandle {
+        &self.handle
+    }
+
+    /// Detach the underlying handle from the channel.
+    pub fn into_handle(self) -> EventHandle {
+        self.handle
+    }
+
+    /// Advance the handle and send the derived key (see `EventHandle::event_key_at`) of the
+    /// emitted event on the channel, returning it as well. The handle advances even if the key
+    /// can't be sent because the receiver is gone; that is reported as an error.
+    pub fn emit(&mut self) -> Result<EventKey> {
+        let key = self.handle.event_key_at(self.handle.count);
+        self.handle.count += 1;
+        self.sink.send(key)?;
+        Ok(key)
+    }
+}
+
+/// An `EventKey` tagged with the domain `D` it belongs to, e.g. account events or system events,
+/// so that a key of one domain can't be passed where a key of another one is expected. Crossing
+/// domains requires an explicit `into_raw` followed by `from_raw`.
+pub struct DomainEventKey<D>(EventKey, PhantomData<D>);
+
+impl<D> DomainEventKey<D> {
+    /// Tag `key` as belonging to the domain `D`.
+    pub fn from_raw(key: EventKey) -> Self {
+        DomainEventKey(key, PhantomData)
+    }
+
+    /// Return the untagged key.
+    pub fn into_raw(self) -> EventKey {
+        self.0
+    }
+
+    /// Get the untagged key.
+    pub fn as_raw(&self) -> &EventKey {
+        &self.0
+    }
+}
+
+// The impls below are written out because deriving them would require `D` to implement the
+// respective traits too, although `D` is only ever used as a marker.
+impl<D> Clone for DomainEventKey<D> {
+    fn clone(&self) -> Self {
+        Self::from_raw(self.0)
+    }
+}
+
+impl<D> Copy for DomainEventKey<D> {}
+
+impl<D> PartialEq for DomainEventKey<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<D> Eq for DomainEventKey<D> {}
+
+impl<D> Hash for DomainEventKey<D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<D> fmt::Debug for DomainEventKey<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DomainEventKey").field(&self.0).finish()
+    }
+}
+
 impl fmt::LowerHex for EventKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
         write!(f, "{}", hex::encode(&self.0))
     }
 }
 
+/// Renders the key as `EventKey(0x...)`, i.e. the `Display` form wrapped in the type name.
+impl fmt::Debug for EventKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventKey({:#x})", self)
+    }
+}
+
+/// Renders the key as `0x` followed by its hex encoding. This is the form keys should take in
+/// logs, e.g. as a structured field with `%key`.
 impl fmt::Display for EventKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> std::fmt::Result {
         // Forward to the LowerHex impl with a "0x" prepended (the # flag).
@@ -135,6 +928,43 @@ impl fmt::Display for EventKey {
     }
 }
 
+/// Serializes a map keyed by `EventKey` with the keys as hex strings, so that it can be used
+/// with formats that only admit string map keys such as JSON. Use as
+/// `#[serde(serialize_with = "serialize_event_key_map")]`.
+pub fn serialize_event_key_map<S, V, H>(
+    map: &HashMap<EventKey, V, H>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+    H: BuildHasher,
+{
+    serializer.collect_map(map.iter().map(|(key, value)| (format!("{:x}", key), value)))
+}
+
+/// The inverse of `serialize_event_key_map`. Use as
+/// `#[serde(deserialize_with = "deserialize_event_key_map")]`.
+pub fn deserialize_event_key_map<'de, D, V, H>(
+    deserializer: D,
+) -> std::result::Result<HashMap<EventKey, V, H>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    let encoded: HashMap<String, V> = Deserialize::deserialize(deserializer)?;
+    encoded
+        .into_iter()
+        .map(|(key, value)| {
+            let bytes = hex::decode(&key).map_err(<D::Error as serde::de::Error>::custom)?;
+            let key = EventKey::try_from(bytes.as_slice())
+                .map_err(<D::Error as serde::de::Error>::custom)?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
 impl FromProto for EventKey {
     type ProtoType = Vec<u8>;
 
@@ -151,6 +981,28 @@ impl IntoProto for EventKey {
     }
 }
 
+impl FromProto for EventHandle {
+    type ProtoType = crate::proto::events::EventHandle;
+
+    fn from_proto(mut object: Self::ProtoType) -> Result<Self> {
+        Ok(EventHandle::new(
+            EventKey::from_proto(object.take_key())?,
+            object.get_count(),
+        ))
+    }
+}
+
+impl IntoProto for EventHandle {
+    type ProtoType = crate::proto::events::EventHandle;
+
+    fn into_proto(self) -> Self::ProtoType {
+        let mut out = Self::ProtoType::new();
+        out.set_key(self.key.into_proto());
+        out.set_count(self.count);
+        out
+    }
+}
+
 impl CanonicalSerialize for EventKey {
     fn serialize(&self, serializer: &mut impl CanonicalSerializer) -> Result<()> {
         // We cannot use encode_raw_bytes as this structure will represent how Move Value of type
@@ -168,6 +1020,9 @@ impl CanonicalDeserialize for EventKey {
     }
 }
 
+/// The canonical form matches the layout of the `Event.Handle` Move resource, whose fields are laid
+/// out in alphabetical order: the count, `counter`, comes first as a little-endian `u64`, followed
+/// by the key, `guid`, as a byte array prefixed with its length as a little-endian `u32`.
 impl CanonicalSerialize for EventHandle {
     fn serialize(&self, serializer: &mut impl CanonicalSerializer) -> Result<()> {
         serializer
@@ -184,3 +1039,20 @@ impl CanonicalDeserialize for EventHandle {
         Ok(EventHandle { count, key })
     }
 }
+
+impl CanonicalSerialize for ExpiringEventKey {
+    fn serialize(&self, serializer: &mut impl CanonicalSerializer) -> Result<()> {
+        serializer
+            .encode_struct(&self.key)?
+            .encode_u64(self.expires_at)?;
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for ExpiringEventKey {
+    fn deserialize(deserializer: &mut impl CanonicalDeserializer) -> Result<Self> {
+        let key = deserializer.decode_struct()?;
+        let expires_at = deserializer.decode_u64()?;
+        Ok(ExpiringEventKey { key, expires_at })
+    }
+}
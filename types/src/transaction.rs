@@ -281,6 +281,19 @@ pub enum TransactionPayload {
     Script(Script),
 }
 
+impl TransactionPayload {
+    /// Wraps a compiled program in the payload that carries it with the least overhead: a
+    /// `Script` if it doesn't publish any modules, a `Program` otherwise.
+    pub fn from_program(program: Program) -> Self {
+        if program.modules().is_empty() {
+            let (code, args, _) = program.into_inner();
+            TransactionPayload::Script(Script::new(code, args))
+        } else {
+            TransactionPayload::Program(program)
+        }
+    }
+}
+
 impl CanonicalSerialize for TransactionPayload {
     fn serialize(&self, serializer: &mut impl CanonicalSerializer) -> Result<()> {
         match self {
@@ -0,0 +1,13 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::event::EventHandle;
+use proptest::prelude::*;
+use proto_conv::test_helper::assert_protobuf_encode_decode;
+
+proptest! {
+    #[test]
+    fn test_event_handle(handle in any::<EventHandle>()) {
+        assert_protobuf_encode_decode(&handle);
+    }
+}
@@ -0,0 +1,641 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_address::AccountAddress,
+    event::{
+        deserialize_event_key_map, local_summary, serialize_event_key_map, sweep_expired,
+        total_serialized_size, CountIndex, DomainEventKey, EventEmitter, EventHandle, EventKey,
+        EventKeySet, EventRing, EventVisitor, ExpiringEventKey, TypedEventEmitter,
+        EVENT_KEY_LENGTH,
+    },
+};
+use canonical_serialization::{
+    CanonicalDeserialize, CanonicalDeserializer, CanonicalSerialize, CanonicalSerializer,
+    SimpleDeserializer, SimpleSerializer,
+};
+use failure::prelude::*;
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::TypeId,
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    sync::mpsc,
+};
+
+#[derive(Debug, PartialEq)]
+struct TransferEvent {
+    amount: u64,
+    payee: AccountAddress,
+}
+
+impl CanonicalSerialize for TransferEvent {
+    fn serialize(&self, serializer: &mut impl CanonicalSerializer) -> Result<()> {
+        serializer
+            .encode_u64(self.amount)?
+            .encode_struct(&self.payee)?;
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for TransferEvent {
+    fn deserialize(deserializer: &mut impl CanonicalDeserializer) -> Result<Self> {
+        let amount = deserializer.decode_u64()?;
+        let payee = deserializer.decode_struct()?;
+        Ok(TransferEvent { amount, payee })
+    }
+}
+
+#[test]
+fn test_event_emitter_round_trip() {
+    let handle = EventHandle::new(EventKey::random(), 0);
+    let mut emitter = EventEmitter::new(handle.clone());
+    let payee = AccountAddress::random();
+
+    let (key, payload) = emitter.emit(TransferEvent { amount: 7, payee }).unwrap();
+    assert_eq!(key, handle.event_key_at(0));
+    assert_eq!(emitter.handle().count(), 1);
+    assert_eq!(
+        SimpleDeserializer::deserialize::<TransferEvent>(&payload).unwrap(),
+        TransferEvent { amount: 7, payee }
+    );
+
+    let (key, _) = emitter.emit(TransferEvent { amount: 8, payee }).unwrap();
+    assert_eq!(key, handle.event_key_at(1));
+    assert_eq!(emitter.into_handle().count(), 2);
+}
+
+#[test]
+fn test_event_key_set_operations() {
+    let keys: Vec<_> = (0..4).map(|_| EventKey::random()).collect();
+    let local: EventKeySet = keys[..3].iter().copied().collect();
+    let remote: EventKeySet = keys[1..].iter().copied().collect();
+
+    let only_local = local.difference(&remote);
+    assert_eq!(only_local.len(), 1);
+    assert!(only_local.contains(&keys[0]));
+
+    let only_remote = remote.difference(&local);
+    assert_eq!(only_remote.len(), 1);
+    assert!(only_remote.contains(&keys[3]));
+
+    let both = local.intersection(&remote);
+    assert_eq!(both.len(), 2);
+    assert!(both.contains(&keys[1]) && both.contains(&keys[2]));
+
+    let all = local.union(&remote);
+    assert_eq!(all, keys.iter().copied().collect());
+    assert!(local.difference(&all).is_empty());
+}
+
+#[test]
+fn test_find_gaps() {
+    let handle = EventHandle::new(EventKey::random(), 6);
+    let received: BTreeSet<_> = [0, 2, 3, 5]
+        .iter()
+        .map(|seq| handle.event_key_at(*seq))
+        .collect();
+    assert_eq!(handle.find_gaps(&received), vec![1, 4]);
+
+    let all: BTreeSet<_> = (0..6).map(|seq| handle.event_key_at(seq)).collect();
+    assert!(handle.find_gaps(&all).is_empty());
+    assert_eq!(handle.find_gaps(&BTreeSet::new()).len(), 6);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct EventIndex {
+    #[serde(serialize_with = "serialize_event_key_map")]
+    #[serde(deserialize_with = "deserialize_event_key_map")]
+    counts: HashMap<EventKey, u64>,
+}
+
+#[test]
+fn test_event_key_map_json_round_trip() {
+    let key = EventKey::new([0xab; 32]);
+    let mut counts = HashMap::new();
+    counts.insert(key, 3);
+    counts.insert(EventKey::random(), 5);
+    let index = EventIndex { counts };
+
+    let json = serde_json::to_string(&index).unwrap();
+    assert!(json.contains(&format!("\"{}\":3", "ab".repeat(32))));
+    assert_eq!(serde_json::from_str::<EventIndex>(&json).unwrap(), index);
+}
+
+#[test]
+fn test_checkpoint_restore() {
+    let handle = EventHandle::new(EventKey::new([7u8; 32]), 42);
+    let bytes = handle.checkpoint();
+    assert_eq!(EventHandle::restore(&bytes).unwrap(), handle);
+
+    let mut bad_version = bytes.clone();
+    bad_version[0] = 0;
+    assert!(EventHandle::restore(&bad_version).is_err());
+    assert!(EventHandle::restore(&bytes[..bytes.len() - 1]).is_err());
+}
+
+#[test]
+fn test_sweep_expired_keys() {
+    let mut keys = vec![
+        ExpiringEventKey::new(EventKey::random(), 10),
+        ExpiringEventKey::new(EventKey::random(), 20),
+        ExpiringEventKey::new(EventKey::random(), 30),
+    ];
+    assert!(!keys[1].is_expired(19));
+    assert!(keys[1].is_expired(20));
+
+    let unexpired = keys[1..].to_vec();
+    sweep_expired(&mut keys, 20);
+    assert_eq!(keys, unexpired);
+    sweep_expired(&mut keys, 5);
+    assert_eq!(keys, unexpired);
+}
+
+#[test]
+fn test_expiring_event_key_canonical_round_trip() {
+    let key = ExpiringEventKey::new(EventKey::random(), 1_000);
+    let bytes = SimpleSerializer::<Vec<u8>>::serialize(&key).unwrap();
+    assert_eq!(
+        SimpleDeserializer::deserialize::<ExpiringEventKey>(&bytes).unwrap(),
+        key
+    );
+}
+
+#[test]
+fn test_reserve_matches_emission() {
+    let mut handle = EventHandle::new(EventKey::random(), 2);
+    let mut emitter = EventEmitter::new(handle.clone());
+    let payee = AccountAddress::random();
+
+    let reservation = handle.reserve(3);
+    assert_eq!(reservation.start(), 2);
+    assert_eq!(handle.count(), 5);
+
+    let emitted: Vec<_> = (0..3)
+        .map(|amount| emitter.emit(TransferEvent { amount, payee }).unwrap().0)
+        .collect();
+    assert_eq!(reservation.keys(), &emitted[..]);
+    assert_eq!(emitter.handle(), &handle);
+}
+
+#[test]
+fn test_window_slots() {
+    let handle = EventHandle::new(EventKey::random(), 7);
+    assert_eq!(handle.window_slot(0, 3).unwrap(), 0);
+    assert_eq!(handle.window_slot(2, 3).unwrap(), 0);
+    assert_eq!(handle.window_slot(3, 3).unwrap(), 1);
+    assert_eq!(handle.window_slot(6, 3).unwrap(), 2);
+
+    let windows = handle.keys_by_window(3).unwrap();
+    assert_eq!(windows.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(
+        windows[&1],
+        (3..6)
+            .map(|seq| handle.event_key_at(seq))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(windows[&2], vec![handle.event_key_at(6)]);
+
+    let err = handle.window_slot(0, 0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Event streams can't be cut into windows of 0 events"
+    );
+    // The window size is checked even when there are no events to group.
+    let empty = EventHandle::new(EventKey::random(), 0);
+    assert!(empty.keys_by_window(0).is_err());
+}
+
+#[derive(Default)]
+struct RecordingVisitor {
+    seen: Vec<(u64, EventKey, Option<Vec<u8>>)>,
+}
+
+impl EventVisitor for RecordingVisitor {
+    fn visit(&mut self, index: u64, key: &EventKey, payload: Option<Vec<u8>>) {
+        self.seen.push((index, *key, payload));
+    }
+}
+
+#[test]
+fn test_replay_visits_in_order() {
+    let handle = EventHandle::new(EventKey::random(), 3);
+    let mut store = HashMap::new();
+    store.insert(handle.event_key_at(0), vec![0u8]);
+    store.insert(handle.event_key_at(2), vec![2u8]);
+
+    let mut visitor = RecordingVisitor::default();
+    handle.replay(|key| store.get(key).cloned(), &mut visitor);
+    assert_eq!(
+        visitor.seen,
+        vec![
+            (0, handle.event_key_at(0), Some(vec![0u8])),
+            (1, handle.event_key_at(1), None),
+            (2, handle.event_key_at(2), Some(vec![2u8])),
+        ]
+    );
+}
+
+#[test]
+fn test_checked_bytes_detect_corruption() {
+    let key = EventKey::random();
+    let bytes = key.with_checksum();
+    assert_eq!(EventKey::from_checked_bytes(&bytes).unwrap(), key);
+
+    for idx in &[0, 17, 32] {
+        let mut corrupted = bytes;
+        corrupted[*idx] ^= 0x10;
+        assert!(EventKey::from_checked_bytes(&corrupted).is_err());
+    }
+}
+
+#[test]
+fn test_typed_emit_sequences_are_independent() {
+    let base = EventHandle::new(EventKey::random(), 5);
+    let mut interleaved = TypedEventEmitter::new(base.clone());
+    let keys: Vec<_> = [0, 1, 0, 1, 0]
+        .iter()
+        .map(|type_index| interleaved.emit(*type_index))
+        .collect();
+    assert_eq!(interleaved.handle().count(), 10);
+    assert_eq!(interleaved.type_count(0), 3);
+    assert_eq!(interleaved.type_count(1), 2);
+    assert_eq!(interleaved.type_count(2), 0);
+
+    let mut only_type_0 = TypedEventEmitter::new(base.clone());
+    let type_0: Vec<_> = (0..3).map(|_| only_type_0.emit(0)).collect();
+    let mut only_type_1 = TypedEventEmitter::new(base);
+    let type_1: Vec<_> = (0..2).map(|_| only_type_1.emit(1)).collect();
+    assert_eq!(vec![keys[0], keys[2], keys[4]], type_0);
+    assert_eq!(vec![keys[1], keys[3]], type_1);
+
+    let distinct: BTreeSet<_> = keys.iter().collect();
+    assert_eq!(distinct.len(), keys.len());
+}
+
+#[test]
+fn test_count_index_membership() {
+    let key = EventKey::random();
+    let other = EventKey::random();
+    let counts = [1_000_000, 3, 0, 200, 1_000_001];
+    let mut index = CountIndex::new();
+    for count in &counts {
+        assert!(index.mark_seen(key, *count));
+    }
+    assert!(!index.mark_seen(key, 200));
+
+    for count in &counts {
+        assert!(index.is_seen(&key, *count));
+        assert!(!index.is_seen(&other, *count));
+    }
+    assert!(!index.is_seen(&key, 1));
+    assert!(!index.is_seen(&key, 999_999));
+
+    // Gaps 0, 3, 197, 999_800 and 1 take 1, 1, 2, 3 and 1 bytes.
+    assert_eq!(index.encoded_size(), 8);
+}
+
+#[test]
+fn test_event_ring_keeps_most_recent() {
+    let handle = EventHandle::new(EventKey::random(), 0);
+    let mut ring = EventRing::new(*handle.key(), 3);
+    for count in 0..5 {
+        ring.record(count);
+    }
+    assert_eq!(
+        ring.recent_keys(),
+        (2..5)
+            .map(|seq| handle.event_key_at(seq))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_pseudonymize() {
+    let key = EventKey::random();
+    let pseudonym = key.pseudonymize(b"secret");
+    assert_eq!(key.pseudonymize(b"secret"), pseudonym);
+    assert_ne!(pseudonym, key);
+    assert_ne!(key.pseudonymize(b"other secret"), pseudonym);
+    assert_ne!(EventKey::random().pseudonymize(b"secret"), pseudonym);
+}
+
+#[test]
+fn test_genesis_key_ignores_count() {
+    let key = EventKey::random();
+    let fresh = EventHandle::new(key, 0);
+    let advanced = EventHandle::new(key, 42);
+    assert_eq!(fresh.genesis_key(), advanced.genesis_key());
+    assert_eq!(advanced.genesis_key(), advanced.event_key_at(0));
+    assert_ne!(
+        EventHandle::new(EventKey::random(), 42).genesis_key(),
+        advanced.genesis_key()
+    );
+}
+
+#[test]
+fn test_domain_event_key_round_trip() {
+    struct AccountEvents;
+    struct SystemEvents;
+
+    let raw = EventKey::random();
+    let account_key = DomainEventKey::<AccountEvents>::from_raw(raw);
+    assert_eq!(*account_key.as_raw(), raw);
+    assert_eq!(account_key.into_raw(), raw);
+
+    // Crossing domains has to go through the raw key.
+    let system_key = DomainEventKey::<SystemEvents>::from_raw(account_key.into_raw());
+    assert_eq!(system_key.into_raw(), raw);
+    assert_ne!(
+        TypeId::of::<DomainEventKey<AccountEvents>>(),
+        TypeId::of::<DomainEventKey<SystemEvents>>()
+    );
+}
+
+#[test]
+fn test_with_sink_sends_emitted_keys() {
+    let handle = EventHandle::new(EventKey::random(), 3);
+    let expected: Vec<_> = (3..6).map(|seq| handle.event_key_at(seq)).collect();
+    let (sender, receiver) = mpsc::channel();
+    let mut notifying = handle.with_sink(sender);
+
+    let emitted: Vec<_> = (0..3).map(|_| notifying.emit().unwrap()).collect();
+    assert_eq!(emitted, expected);
+    assert_eq!(receiver.try_iter().collect::<Vec<_>>(), expected);
+    assert_eq!(notifying.handle().count(), 6);
+
+    drop(receiver);
+    assert!(notifying.emit().is_err());
+    assert_eq!(notifying.into_handle().count(), 7);
+}
+
+#[test]
+fn test_stable_hash64() {
+    let mut bytes = [0u8; EVENT_KEY_LENGTH];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    // Fixed value, so that a change of the hash across versions or processes is caught.
+    assert_eq!(EventKey::new(bytes).stable_hash64(), 0xe6cb_594c_1a14_8ac5);
+
+    let mut buckets = [0usize; 10];
+    for _ in 0..10_000 {
+        buckets[(EventKey::random().stable_hash64() % 10) as usize] += 1;
+    }
+    for count in buckets.iter() {
+        assert!(
+            *count > 800 && *count < 1200,
+            "skewed buckets: {:?}",
+            buckets
+        );
+    }
+}
+
+#[test]
+fn test_total_serialized_size() {
+    let handles: Vec<_> = (0..5)
+        .map(|count| EventHandle::random_handle(count * 1000))
+        .collect();
+    let expected: usize = handles
+        .iter()
+        .map(|handle| {
+            SimpleSerializer::<Vec<u8>>::serialize(handle)
+                .unwrap()
+                .len()
+        })
+        .sum();
+    assert_eq!(total_serialized_size(&handles), expected);
+    assert_eq!(total_serialized_size(&[]), 0);
+}
+
+#[test]
+fn test_display_renders_prefixed_hex() {
+    let key = EventKey::new([0xab; EVENT_KEY_LENGTH]);
+    assert_eq!(
+        key.to_string(),
+        format!("0x{}", "ab".repeat(EVENT_KEY_LENGTH))
+    );
+    assert_eq!(format!("{:x}", key), "ab".repeat(EVENT_KEY_LENGTH));
+}
+
+#[test]
+fn test_debug_renders_type_and_hex() {
+    let key = EventKey::new([0xab; EVENT_KEY_LENGTH]);
+    assert_eq!(
+        format!("{:?}", key),
+        format!("EventKey(0x{})", "ab".repeat(EVENT_KEY_LENGTH))
+    );
+}
+
+#[test]
+fn test_sync_delta_in_both_directions() {
+    let shared = EventKey::random();
+    let only_local = EventKey::random();
+    let only_remote = EventKey::random();
+    let local = local_summary(&[
+        EventHandle::new(shared, 10),
+        EventHandle::new(only_local, 4),
+    ]);
+    let remote = local_summary(&[
+        EventHandle::new(shared, 6),
+        EventHandle::new(only_remote, 2),
+        EventHandle::new(only_remote, 3),
+    ]);
+
+    let mut expected = vec![(shared, 6, 10), (only_local, 0, 4)];
+    expected.sort();
+    assert_eq!(local.compute_delta(&remote), expected);
+    assert_eq!(remote.compute_delta(&local), vec![(only_remote, 0, 3)]);
+    assert!(local.compute_delta(&local).is_empty());
+}
+
+#[test]
+fn test_parse_event_key_from_hex() {
+    let key = EventKey::random();
+    assert_eq!(EventKey::from_hex(&format!("{:x}", key)).unwrap(), key);
+    assert_eq!(key.to_string().parse::<EventKey>().unwrap(), key);
+
+    let err = EventKey::from_hex("0xabcd").unwrap_err();
+    assert_eq!(err.to_string(), "Event key 0xabcd has 2 bytes, expected 32");
+    assert!(EventKey::from_hex("0xzz").is_err());
+}
+
+#[test]
+fn test_event_key_length_is_validated() {
+    let bytes = EventKey::random().to_vec();
+    assert_eq!(
+        EventKey::try_from_vec(bytes.clone()).unwrap(),
+        EventKey::try_from(bytes.as_slice()).unwrap()
+    );
+
+    let err = EventKey::try_from_vec(vec![0; 31]).unwrap_err();
+    assert_eq!(err.to_string(), "Event key has 31 bytes, expected 32");
+    let err = EventKey::try_from(&[0u8; 33][..]).unwrap_err();
+    assert_eq!(err.to_string(), "Event key has 33 bytes, expected 32");
+}
+
+#[test]
+fn test_event_key_array_accessors() {
+    let mut bytes = [0; EVENT_KEY_LENGTH];
+    bytes[0] = 1;
+    let key = EventKey::new(bytes);
+    assert_eq!(key.as_array(), &bytes);
+    assert_eq!(key.into_array(), bytes);
+}
+
+#[test]
+fn test_event_key_array_conversions() {
+    let mut bytes = [0; EVENT_KEY_LENGTH];
+    bytes[0] = 1;
+    let key: EventKey = bytes.into();
+    assert_eq!(key, EventKey::new(bytes));
+    let array: [u8; EVENT_KEY_LENGTH] = key.into();
+    assert_eq!(array, bytes);
+}
+
+#[test]
+fn test_event_key_prefix_bounds() {
+    let key = EventKey::random();
+    assert_eq!(key.prefix(0).unwrap(), &[] as &[u8]);
+    assert_eq!(key.prefix(4).unwrap(), &key.as_bytes()[..4]);
+    assert_eq!(key.prefix(EVENT_KEY_LENGTH).unwrap(), key.as_bytes());
+    let err = key.prefix(EVENT_KEY_LENGTH + 1).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Event key prefix of 33 bytes requested, keys have 32"
+    );
+}
+
+#[test]
+fn test_event_key_shard_is_deterministic() {
+    let mut bytes = [0xff; EVENT_KEY_LENGTH];
+    bytes[..8].copy_from_slice(&10u64.to_be_bytes());
+    let key = EventKey::new(bytes);
+    assert_eq!(key.shard(4).unwrap(), 2);
+    assert_eq!(key.shard(1).unwrap(), 0);
+    let err = key.shard(0).unwrap_err();
+    assert_eq!(err.to_string(), "Event keys can't be sharded into 0 shards");
+
+    for _ in 0..100 {
+        let key = EventKey::random();
+        let shard = key.shard(16).unwrap();
+        assert_eq!(shard, key.shard(16).unwrap());
+        assert!(shard < 16);
+    }
+}
+
+#[test]
+fn test_genesis_key_is_zero() {
+    assert_eq!(EventKey::genesis(), EventKey::default());
+    assert!(EventKey::genesis().is_zero());
+
+    let mut bytes = [0; EVENT_KEY_LENGTH];
+    bytes[EVENT_KEY_LENGTH - 1] = 1;
+    assert!(!EventKey::new(bytes).is_zero());
+}
+
+#[test]
+fn test_ct_eq_agrees_with_eq() {
+    for _ in 0..100 {
+        let (a, b) = (EventKey::random(), EventKey::random());
+        assert_eq!(a.ct_eq(&b), a == b);
+        assert!(a.ct_eq(&a));
+    }
+
+    let mut bytes = [7; EVENT_KEY_LENGTH];
+    let key = EventKey::new(bytes);
+    bytes[EVENT_KEY_LENGTH - 1] = 8;
+    assert!(!key.ct_eq(&EventKey::new(bytes)));
+}
+
+#[test]
+fn test_new_from_address_is_deterministic() {
+    let addr = AccountAddress::random();
+    let key = EventKey::new_from_address(&addr, 7);
+    assert_eq!(EventKey::new_from_address(&addr, 7), key);
+    assert_ne!(EventKey::new_from_address(&addr, 8), key);
+    assert_eq!(*EventHandle::new_from_address(&addr, 7).key(), key);
+    assert_eq!(EventHandle::new_from_address(&addr, 7).count(), 0);
+}
+
+#[test]
+fn test_try_new_from_address_matches_infallible_version() {
+    let addr = AccountAddress::random();
+    assert_eq!(
+        EventKey::try_new_from_address(&addr, 7).unwrap(),
+        EventKey::new_from_address(&addr, 7)
+    );
+}
+
+#[test]
+fn test_iter_keys_pairs_sequence_numbers_with_stream_key() {
+    let key = EventKey::random();
+    let handle = EventHandle::new(key, 3);
+    let pairs: Vec<_> = handle.iter_keys().collect();
+    assert_eq!(pairs, vec![(0, key), (1, key), (2, key)]);
+    assert_eq!(EventHandle::new(key, 0).iter_keys().count(), 0);
+}
+
+#[test]
+fn test_increment_and_count_mut() {
+    let mut handle = EventHandle::new(EventKey::random(), 0);
+    handle.increment();
+    handle.increment();
+    assert_eq!(handle.count(), 2);
+
+    *handle.count_mut() += 10;
+    assert_eq!(handle.count(), 12);
+}
+
+#[test]
+fn test_event_key_canonical_roundtrip() {
+    let key = EventKey::random();
+    let bytes = SimpleSerializer::<Vec<u8>>::serialize(&key).unwrap();
+    assert_eq!(
+        SimpleDeserializer::deserialize::<EventKey>(&bytes).unwrap(),
+        key
+    );
+}
+
+#[test]
+fn test_event_handle_canonical_bytes() {
+    let handle = EventHandle::new(EventKey::new([0xab; EVENT_KEY_LENGTH]), 0x0102);
+    let bytes = SimpleSerializer::<Vec<u8>>::serialize(&handle).unwrap();
+
+    // The count, then the length-prefixed key, as in the layout of the Move resource.
+    let mut expected = vec![0x02, 0x01, 0, 0, 0, 0, 0, 0];
+    expected.extend_from_slice(&[EVENT_KEY_LENGTH as u8, 0, 0, 0]);
+    expected.extend_from_slice(&[0xab; EVENT_KEY_LENGTH]);
+    assert_eq!(bytes, expected);
+    assert_eq!(
+        SimpleDeserializer::deserialize::<EventHandle>(&bytes).unwrap(),
+        handle
+    );
+}
+
+#[test]
+fn test_event_key_canonical_bytes() {
+    let key = EventKey::new([0xab; EVENT_KEY_LENGTH]);
+    let bytes = SimpleSerializer::<Vec<u8>>::serialize(&key).unwrap();
+
+    // Length-prefixed, as Move byte arrays are, rather than the raw bytes.
+    let mut expected = vec![EVENT_KEY_LENGTH as u8, 0, 0, 0];
+    expected.extend_from_slice(&[0xab; EVENT_KEY_LENGTH]);
+    assert_eq!(bytes, expected);
+    assert_eq!(
+        SimpleDeserializer::deserialize::<EventKey>(&bytes).unwrap(),
+        key
+    );
+
+    // The raw bytes without the prefix don't deserialize.
+    assert!(SimpleDeserializer::deserialize::<EventKey>(&[0xab; EVENT_KEY_LENGTH]).is_err());
+}
+
+proptest! {
+    #[test]
+    fn test_arbitrary_event_handle_roundtrip(handle in any::<EventHandle>()) {
+        let bytes = SimpleSerializer::<Vec<u8>>::serialize(&handle).unwrap();
+        prop_assert_eq!(SimpleDeserializer::deserialize::<EventHandle>(&bytes).unwrap(), handle);
+    }
+}